@@ -0,0 +1,55 @@
+//! Time the parsing of a large generated html page, where most elements
+//! are no icon declarations. Run with `cargo bench --bench parse_large_html`,
+//! optionally followed by `-- ELEMENTS RUNS`, by default 20000 elements and 20 runs.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use website_icon_extract::parse::{icon_hrefs, ParseOptions};
+
+/// Page with a few icons and unrelated tags in the head and `elements` elements in the body
+fn large_page(elements: usize) -> String {
+    let mut html = String::from(
+        r#"<!DOCTYPE html><html><head><meta charset="utf-8"><title>Benchmark</title>
+<meta name="viewport" content="width=device-width"><meta property="og:title" content="Benchmark">
+<link rel="stylesheet" href="/style.css"><link rel="icon" href="/favicon.png" sizes="32x32">
+<link rel="apple-touch-icon" href="/apple-touch-icon.png"><meta property="og:image" content="/og.jpg">
+</head><body>"#,
+    );
+    for i in 0..elements {
+        let element = match i % 4 {
+            0 => format!(r#"<div class="row item-{}" id="item-{}" data-index="{}">"#, i % 7, i, i),
+            1 => format!(r#"<a href="/article/{}" title="Article {}">Article {}</a>"#, i, i, i),
+            2 => format!(r#"<img src="/images/{}.jpg" alt="Picture {}" width="320" height="240">"#, i, i),
+            _ => String::from("<p>Lorem ipsum dolor sit amet, consectetur adipiscing elit.</p></div>"),
+        };
+        html.push_str(&element);
+        html.push('\n');
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+fn main() {
+    // `cargo bench` passes `--bench` to benchmarks without a harness
+    let mut args = std::env::args().skip(1).filter(|arg| !arg.starts_with("--"));
+    let elements = args.next().and_then(|arg| arg.parse().ok()).unwrap_or(20_000);
+    let runs: u32 = args.next().and_then(|arg| arg.parse().ok()).unwrap_or(20);
+
+    let html = large_page(elements);
+    let options = ParseOptions::default();
+    let found = icon_hrefs(&html, &options).len();
+    let start = Instant::now();
+    for _ in 0..runs {
+        black_box(icon_hrefs(black_box(&html), &options));
+    }
+    let per_run = start.elapsed() / runs.max(1);
+    println!(
+        "{} elements, {} KiB, {} icons: {:.2} ms per parse over {} runs",
+        elements,
+        html.len() / 1024,
+        found,
+        per_run.as_secs_f64() * 1000.0,
+        runs
+    );
+}
@@ -1,23 +1,134 @@
-use ::website_icon_extract::ImageLink;
+use ::website_icon_extract::{ExtractOptions, IconListExt, ImageLink};
 
-fn main() {
-    let mut args = std::env::args();
-    if args.len() != 2 {
-        println!("needs 1 parameter");
-        std::process::exit(1);
+const USAGE: &str = "usage: extract [--json] [--all | --best] [--timeout SECONDS] [--user-agent AGENT] URL";
+
+const HELP: &str = "Print the icons of a website
+
+options:
+  --json               print a json array of objects with url, type, width and height
+  --all                print all icons, the default
+  --best               print only the best icon
+  --timeout SECONDS    http timeout, 10 by default
+  --user-agent AGENT   user agent header of the requests
+  --help               print this help
+
+exit status:
+  0  icons were found
+  1  the website has no icons
+  2  the arguments are invalid or the page could not be loaded";
+
+/// Exit status if icons were found
+const EXIT_FOUND: i32 = 0;
+/// Exit status if the website has no icons
+const EXIT_NONE: i32 = 1;
+/// Exit status for invalid arguments and failed extractions
+const EXIT_ERROR: i32 = 2;
+
+/// Settings given on the command line
+struct Args {
+    url: String,
+    json: bool,
+    best: bool,
+    timeout: u64,
+    user_agent: String,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut url = None;
+    let mut json = false;
+    let mut best = false;
+    let mut timeout = 10;
+    let mut user_agent = String::from("agent");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--all" => best = false,
+            "--best" => best = true,
+            "--help" => {
+                println!("{}\n\n{}", USAGE, HELP);
+                std::process::exit(EXIT_FOUND);
+            }
+            "--timeout" => {
+                let value = args.next().ok_or("--timeout needs a value")?;
+                timeout = value.parse().map_err(|_| format!("invalid timeout: {}", value))?;
+            }
+            "--user-agent" => user_agent = args.next().ok_or("--user-agent needs a value")?,
+            _ if arg.starts_with("--") => return Err(format!("unknown option: {}", arg)),
+            _ if url.is_none() => url = Some(arg),
+            _ => return Err(String::from("needs 1 url")),
+        }
     }
-    let url: String = args.nth(1).expect("needs 1 parameter");
-    let result = ImageLink::from_website(url, "agent", 10);
+    Ok(Args {
+        url: url.ok_or("needs 1 url")?,
+        json,
+        best,
+        timeout,
+        user_agent,
+    })
+}
 
-    match result {
-        Ok(result) => {
-            for item in result {
-                println!("{:?}", item);
+/// Quote a string for json
+fn json_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Icon as a json object, the type is `null` for vector images
+fn json_icon(icon: &ImageLink) -> String {
+    let image_type = match icon.image_type {
+        Some(image_type) => json_string(&format!("{:?}", image_type).to_lowercase()),
+        None => String::from("null"),
+    };
+    format!(
+        "{{\"url\":{},\"type\":{},\"width\":{},\"height\":{}}}",
+        json_string(icon.url.as_str()),
+        image_type,
+        icon.width,
+        icon.height
+    )
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            std::process::exit(EXIT_ERROR);
+        }
+    };
+    let options = ExtractOptions::new(&args.user_agent, args.timeout);
+    let result = ImageLink::from_website_with_options(&args.url, &options);
 
+    match result {
+        Ok(mut result) => {
+            if args.best {
+                result = result.best().into_iter().collect();
+            }
+            if args.json {
+                let icons: Vec<String> = result.iter().map(json_icon).collect();
+                println!("[{}]", icons.join(","));
+            } else {
+                for item in &result {
+                    println!("{:?}", item);
+                }
+            }
+            if result.is_empty() {
+                std::process::exit(EXIT_NONE);
             }
         }
         Err(err) => {
-            println!("Error: {}", err);
+            eprintln!("Error: {}", err);
+            std::process::exit(EXIT_ERROR);
         }
     }
 }
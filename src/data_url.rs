@@ -0,0 +1,52 @@
+//! Decoding of inline `data:` urls, so embedded icons can be measured without a request.
+//! Both the base64 form `data:image/png;base64,...` and the percent-encoded form
+//! `data:image/svg+xml,%3Csvg...` are supported.
+
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+
+/// Base64 decoder that accepts payloads with and without padding
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Content of a decoded data url
+pub(crate) struct DataUrl {
+    /// Declared media type, lowercase and without parameters
+    pub media_type: String,
+    /// Decoded payload
+    pub data: Vec<u8>,
+}
+
+/// Decode a data url, `None` if it is malformed
+pub(crate) fn decode(url: &Url) -> Option<DataUrl> {
+    let rest = url.as_str().strip_prefix("data:")?;
+    let (header, payload) = rest.split_once(',')?;
+    let mut params = header.split(';');
+    let media_type = params.next().unwrap_or("").trim().to_lowercase();
+    let is_base64 = params.any(|param| param.trim().eq_ignore_ascii_case("base64"));
+
+    let bytes: Vec<u8> = percent_decode_str(payload).collect();
+    let data = if is_base64 {
+        let cleaned: Vec<u8> = bytes
+            .into_iter()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect();
+        BASE64.decode(cleaned).ok()?
+    } else {
+        bytes
+    };
+    Some(DataUrl {
+        media_type: if media_type.is_empty() {
+            String::from("text/plain")
+        } else {
+            media_type
+        },
+        data,
+    })
+}
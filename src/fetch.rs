@@ -0,0 +1,246 @@
+//! Transport behind all requests of an extraction.
+//! [`IconFetcher`] can be implemented to serve pages and icons from something
+//! else than the network, for example from fixtures in tests.
+
+use std::fmt;
+use std::io::Read;
+
+use log::trace;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{HeaderMap, ACCEPT_ENCODING, LOCATION, RANGE, USER_AGENT};
+use reqwest::{Method, Url};
+
+use crate::parse::head_end;
+use crate::IconError;
+
+/// Size of the chunks a page is read in when looking for the end of the head
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Response of an [`IconFetcher`]
+#[derive(Clone, Debug)]
+pub struct FetchedResponse {
+    /// Url the body was read from, after following redirects
+    pub url: Url,
+    /// Http status code
+    pub status: u16,
+    /// Response headers
+    pub headers: HeaderMap,
+    /// Body, possibly only the start of it
+    pub body: Vec<u8>,
+}
+
+/// Performs the requests of an extraction, see [`crate::ExtractOptions::fetcher`].
+/// The default implementation uses a reqwest client.
+/// # Example
+/// ```rust
+/// use website_icon_extract::{ExtractOptions, FetchedResponse, HeaderMap, IconError, IconFetcher, ImageLink, Url};
+/// use std::sync::Arc;
+///
+/// #[derive(Debug)]
+/// struct Fixture;
+///
+/// impl IconFetcher for Fixture {
+///     fn fetch_page(&self, url: &Url, _limit: usize) -> Result<FetchedResponse, IconError> {
+///         let mut headers = HeaderMap::new();
+///         headers.insert("content-type", "text/html".parse().unwrap());
+///         let body = br#"<link rel="icon" href="/icon.png">"#.to_vec();
+///         Ok(FetchedResponse { url: url.clone(), status: 200, headers, body })
+///     }
+///
+///     fn fetch_range(&self, url: &Url, _length: u64) -> Result<FetchedResponse, IconError> {
+///         if url.path() != "/icon.png" {
+///             return Ok(FetchedResponse { url: url.clone(), status: 404, headers: HeaderMap::new(), body: Vec::new() });
+///         }
+///         let body = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x40\0\0\0\x40".to_vec();
+///         Ok(FetchedResponse { url: url.clone(), status: 200, headers: HeaderMap::new(), body })
+///     }
+/// }
+///
+/// let options = ExtractOptions {
+///     fetcher: Some(Arc::new(Fixture)),
+///     ..Default::default()
+/// };
+/// let icons = ImageLink::from_website_with_options("https://example.com/", &options).unwrap();
+/// assert_eq!(icons.len(), 1);
+/// assert_eq!((icons[0].width, icons[0].height), (64, 64));
+/// ```
+pub trait IconFetcher: fmt::Debug + Send + Sync {
+    /// Fetch a page, following redirects. At most `limit` bytes of the body are read.
+    fn fetch_page(&self, url: &Url, limit: usize) -> Result<FetchedResponse, IconError>;
+
+    /// Fetch a page with another method than GET, optionally sending a body,
+    /// see [`crate::ExtractOptions::page_method`].
+    /// The default implementation ignores both and calls [`IconFetcher::fetch_page`].
+    fn fetch_page_with(
+        &self,
+        url: &Url,
+        limit: usize,
+        _method: &Method,
+        _body: Option<&[u8]>,
+    ) -> Result<FetchedResponse, IconError> {
+        self.fetch_page(url, limit)
+    }
+
+    /// Fetch a page like [`IconFetcher::fetch_page`], but only its head.
+    /// The body ends before the closing `</head>` tag if there is one.
+    /// The default implementation fetches the page and cuts it,
+    /// implementations should stop the download instead.
+    fn fetch_head(&self, url: &Url, limit: usize) -> Result<FetchedResponse, IconError> {
+        let mut response = self.fetch_page(url, limit)?;
+        if let Some(end) = head_end(&response.body, 0) {
+            response.body.truncate(end);
+        }
+        Ok(response)
+    }
+
+    /// Fetch the first `length` bytes of a file, with a range request if possible.
+    /// The body may be longer or shorter than `length`.
+    fn fetch_range(&self, url: &Url, length: u64) -> Result<FetchedResponse, IconError>;
+}
+
+/// Default fetcher using a reqwest client
+#[derive(Debug)]
+pub(crate) struct ReqwestFetcher {
+    pub client: Client,
+    /// User agent overriding the one of the client
+    pub user_agent: Option<String>,
+    /// Ask for an uncompressed body in range requests,
+    /// see [`crate::ExtractOptions::identity_encoding_for_probes`]
+    pub identity_ranges: bool,
+}
+
+impl ReqwestFetcher {
+    /// Start a GET request with the user agent of this fetcher
+    fn get(&self, url: Url) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    /// Start a request with the user agent of this fetcher
+    fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.user_agent {
+            Some(user_agent) => request.header(USER_AGENT, user_agent),
+            None => request,
+        }
+    }
+
+    fn get_range(&self, url: Url, length: u64) -> Result<Response, reqwest::Error> {
+        let mut request = self.get(url).header(RANGE, format!("bytes=0-{}", length.saturating_sub(1)));
+        if self.identity_ranges {
+            request = request.header(ACCEPT_ENCODING, "identity");
+        }
+        request.send()
+    }
+}
+
+impl IconFetcher for ReqwestFetcher {
+    fn fetch_page(&self, url: &Url, limit: usize) -> Result<FetchedResponse, IconError> {
+        let response = self.get(url.clone()).send()?;
+        read_response(response, limit as u64)
+    }
+
+    fn fetch_page_with(
+        &self,
+        url: &Url,
+        limit: usize,
+        method: &Method,
+        body: Option<&[u8]>,
+    ) -> Result<FetchedResponse, IconError> {
+        let mut request = self.request(method.clone(), url.clone());
+        if let Some(body) = body {
+            request = request.body(body.to_vec());
+        }
+        read_response(request.send()?, limit as u64)
+    }
+
+    fn fetch_head(&self, url: &Url, limit: usize) -> Result<FetchedResponse, IconError> {
+        let mut response = self.get(url.clone()).send()?;
+        let mut body: Vec<u8> = Vec::new();
+        let mut chunk = [0; CHUNK_SIZE];
+        while body.len() < limit {
+            let wanted = CHUNK_SIZE.min(limit - body.len());
+            let read = response.read(&mut chunk[..wanted])?;
+            if read == 0 {
+                break;
+            }
+            // the closing tag may start in the previous chunk
+            let from = body.len().saturating_sub(b"</head".len());
+            body.extend_from_slice(&chunk[..read]);
+            if let Some(end) = head_end(&body, from) {
+                trace!("{}, end of head after {} bytes, skipping the rest of the page", url, end);
+                body.truncate(end);
+                break;
+            }
+        }
+        Ok(FetchedResponse {
+            url: response.url().clone(),
+            status: response.status().as_u16(),
+            headers: response.headers().clone(),
+            body,
+        })
+    }
+
+    fn fetch_range(&self, url: &Url, length: u64) -> Result<FetchedResponse, IconError> {
+        let mut response = self.get_range(url.clone(), length)?;
+        if let Some(location) = unfollowed_redirect(&response) {
+            // the http client did not follow the redirect, usually because of a
+            // malformed Location, so resolve it against the url that sent it, which
+            // differs from the request url after followed redirects, and retry once
+            trace!("{}, following malformed redirect to {}", response.url(), location);
+            let target = response.url().join(&location)?;
+            response = self.get_range(target, length)?;
+        }
+        let partial = response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+        if response.status() == reqwest::StatusCode::OK {
+            // the server ignored the range, the read below still stops after `length` bytes
+            trace!("{}, no range support, reading only the first {} bytes", url, length);
+        }
+        match read_response(response, length) {
+            Err(IconError::Io(err)) if partial && is_decoding_error(&err) => {
+                // the server compressed the requested range, which can not be decompressed
+                // on its own, so refetch without Range, reading the first `length` bytes
+                trace!(
+                    "{}, compressed partial response, refetching without range, reading the first {} bytes",
+                    url,
+                    length
+                );
+                read_response(self.get(url.clone()).send()?, length)
+            }
+            result => result,
+        }
+    }
+}
+
+/// Read at most `limit` bytes of the body
+fn read_response(response: Response, limit: u64) -> Result<FetchedResponse, IconError> {
+    let url = response.url().clone();
+    let status = response.status().as_u16();
+    let headers = response.headers().clone();
+    let mut body: Vec<u8> = Vec::new();
+    response.take(limit).read_to_end(&mut body)?;
+    Ok(FetchedResponse {
+        url,
+        status,
+        headers,
+        body,
+    })
+}
+
+/// Whether reading a body failed while decompressing its `Content-Encoding`.
+/// The http client removes that header from responses it decompresses,
+/// so the error is the only sign of it.
+fn is_decoding_error(err: &std::io::Error) -> bool {
+    match err.get_ref().and_then(|inner| inner.downcast_ref::<reqwest::Error>()) {
+        Some(err) => err.is_decode() && !err.is_timeout(),
+        None => false,
+    }
+}
+
+/// Location of a redirect response that was handed back instead of being followed
+fn unfollowed_redirect(response: &Response) -> Option<String> {
+    if !response.status().is_redirection() {
+        return None;
+    }
+    let location = response.headers().get(LOCATION)?;
+    Some(String::from_utf8_lossy(location.as_bytes()).trim().to_string())
+}
@@ -6,6 +6,8 @@
 //!   [Apple docs](https://developer.apple.com/library/content/documentation/AppleApplications/Reference/SafariWebContent/ConfiguringWebApplications/ConfiguringWebApplications.html)
 //! * Open graph image
 //!   [ogp.me](http://ogp.me/)
+//! * Twitter card image and `og:logo`
+//!   [developer.twitter.com](https://developer.twitter.com/en/docs/twitter-for-websites/cards/overview/markup)
 //! * Windows 8 tile images
 //!   [Microsoft technet](https://technet.microsoft.com/en-us/windows/dn255024(v=vs.60)#msapplication-TileImage)
 //!
@@ -13,251 +15,2736 @@
 //! They get analyzed for pixel size and only the necessary bytes are downloaded for that to happen.
 //! 
 //! # Example
-//! ```rust
+//! ```rust,no_run
 //! let url = "https://google.com";
-//! let list = ImageLink::from_website(url, "TEST", 5).unwrap();
-//! println("{:?}", list);
+//! let list = website_icon_extract::extract_icons(url, "TEST", 5).unwrap();
+//! println!("{:?}", list);
 //! ```
 
+mod data_url;
+mod fetch;
+pub mod parse;
+mod pool;
+pub mod prelude;
+mod rate;
+#[cfg(feature = "image-processing")]
+mod resize;
+
+use encoding_rs::Encoding;
+use encoding_rs::UTF_8;
 use imagesize::blob_size;
 use imagesize::image_type;
+use imagesize::ImageError;
 use imagesize::ImageSize;
 pub use imagesize::ImageType;
-use log::trace;
+pub use fetch::{FetchedResponse, IconFetcher};
+pub use parse::{PageMetadata, ParseOptions};
+pub use parse::{DEFAULT_APPLE_TOUCH_RELS, DEFAULT_LINK_RELS, DEFAULT_META_NAMES, DEFAULT_META_PROPERTIES};
+pub use parse::{DEFAULT_SOCIAL_META_NAMES, DEFAULT_SOCIAL_META_PROPERTIES};
+use log::{trace, warn};
 
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::error::Error;
-use std::time::Duration;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use reqwest::blocking::Client;
-use reqwest::blocking::Response;
+use reqwest::blocking::{Client, ClientBuilder};
+pub use reqwest::header::HeaderMap;
+pub use reqwest::Identity;
+pub use reqwest::Method;
+pub use reqwest::Proxy;
+use reqwest::header::HeaderName;
+use reqwest::header::CONTENT_LENGTH;
+use reqwest::header::CONTENT_RANGE;
 use reqwest::header::CONTENT_TYPE;
-use reqwest::header::RANGE;
-use reqwest::header::USER_AGENT;
-use reqwest::IntoUrl;
-use reqwest::Url;
+use reqwest::header::SERVER;
+use reqwest::redirect::Policy;
+use reqwest::StatusCode;
+pub use reqwest::Url;
 
 use quick_xml::events::Event;
 use quick_xml::Reader;
 
-/// Holds information about an image
+use fetch::ReqwestFetcher;
+use parse::{analyze_page, attr_to_hash, image_type_from_mime, sitemap_locations, IconRef, ParsedPage};
+use rate::{HostLimitedFetcher, HostLimiter, RateLimitedFetcher, RateLimiter};
+
+/// Errors that can happen while fetching pages and icons
 #[derive(Debug)]
+pub enum IconError {
+    /// Url could not be parsed or joined
+    Url(url::ParseError),
+    /// Http request failed
+    Http(reqwest::Error),
+    /// TLS handshake failed, for example because of an expired or self-signed certificate.
+    /// Icon requests can accept such certificates with [`ExtractOptions::accept_invalid_icon_certs`].
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, IconError, ImageLink};
+    ///
+    /// match ImageLink::new_with_options("https://expired.badssl.com/favicon.ico", &ExtractOptions::default()) {
+    ///     Err(IconError::Tls(err)) => eprintln!("certificate rejected: {}", err),
+    ///     result => println!("{:?}", result),
+    /// }
+    /// ```
+    Tls(reqwest::Error),
+    /// Redirects led back to an url that was already requested, or there were
+    /// more than [`ExtractOptions::max_redirects`] of them
+    TooManyRedirects(reqwest::Error),
+    /// Downloaded data could not be analyzed as an image
+    Image(ImageError),
+    /// Server answered with an empty body instead of an image
+    EmptyBody,
+    /// Icon reference is empty or only a fragment like the `#icon` of an svg sprite,
+    /// and would point to the page itself
+    /// # Example
+    /// ```rust
+    /// use website_icon_extract::{ExtractOptions, IconError, ImageLink, Url};
+    ///
+    /// let base = Url::parse("https://example.com/").unwrap();
+    /// let result = ImageLink::resolve_and_measure(&base, "#icon", &ExtractOptions::default());
+    /// assert!(matches!(result, Err(IconError::EmptyHref)));
+    /// ```
+    EmptyHref,
+    /// Icon reference contains a marker of a template that was not filled in on the server,
+    /// like `{{ favicon }}`, `${base}`, `<%= icon %>` or `%ASSET_URL%`. Such an href is not fetched.
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, IconError, ImageLink};
+    ///
+    /// let result = ImageLink::from_website_detailed("https://example.com", &ExtractOptions::default()).unwrap();
+    /// for (url, error) in &result.errors {
+    ///     if let IconError::TemplatePlaceholder(href) = error {
+    ///         println!("{} is not filled in: {}", url, href);
+    ///     }
+    /// }
+    /// ```
+    TemplatePlaceholder(String),
+    /// Server reports a file size below [`ExtractOptions::min_icon_bytes`]
+    TooSmall(u64),
+    /// Inline `data:` url could not be decoded
+    InvalidDataUrl,
+    /// Reading a response body failed
+    Io(std::io::Error),
+    /// Image format is not in [`ExtractOptions::allowed_types`]
+    DisallowedType(String),
+    /// Encoding label is not known to the WHATWG encoding standard
+    UnknownEncoding(String),
+    /// Url can not be fetched, like `chrome://` or `android-app://` links.
+    /// Only `http`, `https` and `data` are supported.
+    UnsupportedScheme(String),
+    /// Icon is on another host than the page, see [`ExtractOptions::same_host_only`]
+    ForeignHost(String),
+    /// Page answered with a status outside of 2xx, see [`ExtractOptions::require_success`]
+    PageStatus(u16),
+    /// Image header reports dimensions above [`ExtractOptions::max_dimension`]
+    ImplausibleSize {
+        /// Reported pixel width
+        width: usize,
+        /// Reported pixel height
+        height: usize,
+    },
+    /// Image could not be decoded or encoded for resizing
+    #[cfg(feature = "image-processing")]
+    Decode(image::ImageError),
+}
+
+impl Error for IconError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            IconError::Url(err) => Some(err),
+            IconError::Http(err) => Some(err),
+            IconError::Tls(err) => Some(err),
+            IconError::TooManyRedirects(err) => Some(err),
+            IconError::Image(err) => Some(err),
+            IconError::EmptyBody => None,
+            IconError::EmptyHref => None,
+            IconError::TemplatePlaceholder(_) => None,
+            IconError::TooSmall(_) => None,
+            IconError::InvalidDataUrl => None,
+            IconError::Io(err) => Some(err),
+            IconError::DisallowedType(_) => None,
+            IconError::UnknownEncoding(_) => None,
+            IconError::UnsupportedScheme(_) => None,
+            IconError::ForeignHost(_) => None,
+            IconError::PageStatus(_) => None,
+            IconError::ImplausibleSize { .. } => None,
+            #[cfg(feature = "image-processing")]
+            IconError::Decode(err) => Some(err),
+        }
+    }
+}
+
+impl IconError {
+    /// Whether no connection to the server could be established
+    fn is_connect(&self) -> bool {
+        match self {
+            IconError::Http(err) | IconError::Tls(err) => err.is_connect(),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for IconError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IconError::Url(err) => write!(f, "Invalid url: {}", err),
+            IconError::Http(err) => write!(f, "Http request failed: {}", err),
+            IconError::Tls(err) => write!(f, "TLS handshake failed: {}", err),
+            IconError::TooManyRedirects(err) => write!(f, "Too many redirects: {}", err),
+            IconError::Image(err) => write!(f, "Not an image: {}", err),
+            IconError::EmptyBody => f.write_str("Server returned an empty body"),
+            IconError::EmptyHref => f.write_str("Empty icon reference"),
+            IconError::TemplatePlaceholder(href) => write!(f, "Unfilled template in icon reference: {}", href),
+            IconError::TooSmall(size) => write!(f, "File too small for an icon: {} bytes", size),
+            IconError::InvalidDataUrl => f.write_str("Invalid data url"),
+            IconError::Io(err) => write!(f, "Reading response failed: {}", err),
+            IconError::DisallowedType(image_type) => write!(f, "Image type not allowed: {}", image_type),
+            IconError::UnknownEncoding(label) => write!(f, "Unknown encoding: {}", label),
+            IconError::UnsupportedScheme(scheme) => write!(f, "Unsupported url scheme: {}", scheme),
+            IconError::ForeignHost(host) => write!(f, "Icon on another host: {}", host),
+            IconError::PageStatus(status) => write!(f, "Page answered with status {}", status),
+            IconError::ImplausibleSize { width, height } => {
+                write!(f, "Implausible image size: {}x{}", width, height)
+            }
+            #[cfg(feature = "image-processing")]
+            IconError::Decode(err) => write!(f, "Decoding image failed: {}", err),
+        }
+    }
+}
+
+impl From<url::ParseError> for IconError {
+    fn from(err: url::ParseError) -> IconError {
+        IconError::Url(err)
+    }
+}
+
+impl From<reqwest::Error> for IconError {
+    fn from(err: reqwest::Error) -> IconError {
+        if err.is_redirect() {
+            IconError::TooManyRedirects(err)
+        } else if is_tls_error(&err) {
+            IconError::Tls(err)
+        } else {
+            IconError::Http(err)
+        }
+    }
+}
+
+/// Whether the request failed because of the TLS layer, which reqwest reports as a connect error
+fn is_tls_error(err: &reqwest::Error) -> bool {
+    let mut source = err.source();
+    while let Some(cause) = source {
+        if cause.is::<native_tls::Error>() {
+            return true;
+        }
+        source = cause.source();
+    }
+    false
+}
+
+impl From<std::io::Error> for IconError {
+    fn from(err: std::io::Error) -> IconError {
+        IconError::Io(err)
+    }
+}
+
+impl From<ImageError> for IconError {
+    fn from(err: ImageError) -> IconError {
+        IconError::Image(err)
+    }
+}
+
+#[cfg(feature = "image-processing")]
+impl From<image::ImageError> for IconError {
+    fn from(err: image::ImageError) -> IconError {
+        IconError::Decode(err)
+    }
+}
+
+/// Where a reference to an icon was found
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IconSource {
+    /// `<link rel="icon">` or `<link rel="shortcut icon">`
+    Link,
+    /// `<link rel="apple-touch-icon">`
+    AppleTouch,
+    /// `<meta property="og:image">`
+    OpenGraph,
+    /// `<meta name="msapplication-...">` Windows tile images
+    MsTile,
+    /// `<meta name="twitter:image">` or `<meta property="og:logo">`
+    Social,
+    /// `<source srcset="...">` of a `<picture>`, see [`ParseOptions::picture_sources`]
+    Picture,
+    /// `/favicon.ico` in the site root, tried even if not declared
+    DefaultFavicon,
+    /// Url was given directly by the caller
+    Direct,
+}
+
+/// Where the undeclared default `favicon.ico` is looked for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaviconLocation {
+    /// `/favicon.ico` in the site root
+    Root,
+    /// `favicon.ico` in the directory of the page.
+    /// The last path segment of the page url is treated as a directory,
+    /// so `https://example.com/app` probes `https://example.com/app/favicon.ico`.
+    Path,
+    /// Path location first, then the root location
+    Both,
+}
+
+impl FaviconLocation {
+    /// Urls of the default favicon for a page
+    /// # Example
+    /// ```rust
+    /// use website_icon_extract::FaviconLocation;
+    /// use reqwest::Url;
+    ///
+    /// let base = Url::parse("https://example.com/app?x=1").unwrap();
+    /// let urls: Vec<String> = FaviconLocation::Both.urls(&base).iter().map(|u| u.to_string()).collect();
+    /// assert_eq!(urls, vec!["https://example.com/app/favicon.ico", "https://example.com/favicon.ico"]);
+    /// let urls = FaviconLocation::Path.urls(&Url::parse("https://example.com/").unwrap());
+    /// assert_eq!(urls[0].as_str(), "https://example.com/favicon.ico");
+    /// ```
+    pub fn urls(&self, base_url: &Url) -> Vec<Url> {
+        let mut urls: Vec<Url> = Vec::new();
+        if *self != FaviconLocation::Root {
+            let mut directory = base_url.clone();
+            if !directory.path().ends_with('/') {
+                let path = format!("{}/", directory.path());
+                directory.set_path(&path);
+            }
+            urls.extend(directory.join("favicon.ico").ok());
+        }
+        if *self != FaviconLocation::Path {
+            urls.extend(base_url.join("/favicon.ico").ok());
+        }
+        urls.dedup();
+        urls
+    }
+}
+
+/// Resolved icon url that is about to be probed
+#[derive(Clone, Debug)]
+struct Candidate {
+    url: Url,
+    source: IconSource,
+    declared_size: Option<(usize, usize)>,
+    declared_sizes: Vec<(usize, usize)>,
+    declared_type: Option<ImageType>,
+    declared_any: bool,
+}
+
+type SkipFn = dyn Fn(&AuditEntry) + Send + Sync;
+
+/// Callback that is told about every icon candidate that was dropped and why
+#[derive(Clone)]
+pub struct SkipHandler(Arc<SkipFn>);
+
+impl SkipHandler {
+    /// Wrap a closure that is called with the audit entry of each dropped candidate,
+    /// its outcome tells why. Entries of references that could not be resolved have no url.
+    pub fn new<F>(handler: F) -> Self
+    where
+        F: Fn(&AuditEntry) + Send + Sync + 'static,
+    {
+        SkipHandler(Arc::new(handler))
+    }
+}
+
+impl fmt::Debug for SkipHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SkipHandler")
+    }
+}
+
+/// Options used for fetching pages and icons
+#[derive(Clone, Debug)]
+pub struct ExtractOptions {
+    /// User agent header string for http requests
+    pub user_agent: String,
+    /// Http timeout in seconds
+    pub tcp_timeout: u64,
+    /// Client certificate for sites that require mutual-TLS authentication.
+    /// It is sent for the page and for all icon requests.
+    pub identity: Option<Identity>,
+    /// Collapse icons with identical downloaded bytes into one,
+    /// keeping the one with the shortest url.
+    /// Icons without a [`ImageLink::content_hash`] are always kept.
+    /// Icons with the same url are always only fetched once.
+    pub dedup_by_content: bool,
+    /// Largest width or height accepted from an image header.
+    /// Icons reporting more are dropped as corrupt or malicious.
+    pub max_dimension: usize,
+    /// Only return browser-tab-style icons, Open Graph, other social and picture images are skipped
+    pub icons_only: bool,
+    /// Skip Windows tile images
+    pub exclude_ms_tiles: bool,
+    /// Where to look for a `favicon.ico` that the page did not declare
+    pub favicon_location: FaviconLocation,
+    /// Only speak HTTP/1.1, for servers that hang when negotiating HTTP/2
+    pub http1_only: bool,
+    /// Maximum number of bytes read from the html page, the rest is ignored.
+    /// Icons are declared in the head which comes early, so the default of 1 MiB
+    /// protects against huge or endless pages without missing icons.
+    pub max_html_bytes: usize,
+    /// Options for analyzing the html of the page
+    pub parse: ParseOptions,
+    /// Rewrite `http://` icon urls of a `https://` page to `https://` like browsers do,
+    /// the original url is still tried if that fails
+    pub upgrade_insecure: bool,
+    /// If the page declares no icons, look up the first html page listed in
+    /// `/sitemap.xml` and extract from there. At most one extra page is fetched.
+    pub sitemap_fallback: bool,
+    /// Only accept these image formats. The format is sniffed from the downloaded bytes,
+    /// the Content-Type header only counts if the bytes are not recognized. The probe range
+    /// of an icon is downloaded before it is rejected, the check only skips measuring it and
+    /// any further probe requests. Open Graph images declaring another format with
+    /// `og:image:type` are not downloaded at all.
+    /// Scalable images have no [`ImageType`] and are rejected when this is set.
+    /// # Example
+    /// ```rust
+    /// use website_icon_extract::{ExtractOptions, IconError, ImageLink, ImageType};
+    ///
+    /// let options = ExtractOptions {
+    ///     allowed_types: Some(vec![ImageType::Png, ImageType::Ico]),
+    ///     ..Default::default()
+    /// };
+    /// let webp = "data:image/webp,RIFF%00%00%00%00WEBPVP8X%00%00%00%00%00%00%00%00%0F%00%00%0F%00%00";
+    /// let result = ImageLink::new_with_options(webp, &options);
+    /// assert!(matches!(result, Err(IconError::DisallowedType(_))));
+    /// assert!(ImageLink::new_with_options(webp, &ExtractOptions::default()).is_ok());
+    /// ```
+    pub allowed_types: Option<Vec<ImageType>>,
+    /// Take the size of apple touch icons from their `sizes` attribute and skip
+    /// downloading them. Such icons have `measured` set to false and no `image_type`.
+    /// Ignored when `allowed_types` is set, because the format has to be checked.
+    pub trust_declared_sizes: bool,
+    /// Number of icons probed at the same time. Probes run on a pool of at most
+    /// this many threads, no matter how many icons a page declares.
+    /// [`ImageLink::from_websites`] uses it for the number of sites processed at the same time.
+    pub max_concurrency: usize,
+    /// User agents to rotate through in [`ImageLink::from_websites`]. Each host is
+    /// assigned one of them in round-robin order and keeps it for all its requests.
+    /// `user_agent` is used if this is empty and for single extractions.
+    pub user_agents: Vec<String>,
+    /// Called for every candidate that is not kept, whatever the [`AuditOutcome`],
+    /// on the calling thread and in the order of the candidates
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink, SkipHandler};
+    ///
+    /// let options = ExtractOptions {
+    ///     on_skip: Some(SkipHandler::new(|entry| eprintln!("skipped {:?}: {:?}", entry.href, entry.outcome))),
+    ///     ..Default::default()
+    /// };
+    /// let list = ImageLink::from_website_with_options("https://example.com", &options).unwrap();
+    /// ```
+    pub on_skip: Option<SkipHandler>,
+    /// Probe at most this many candidates, in the order described at
+    /// [`ImageLink::from_website_with_options`]. Candidates with the same url are
+    /// merged before the list is cut. `dedup_by_content` runs after probing,
+    /// so it can leave fewer icons than this.
+    pub max_candidates: Option<usize>,
+    /// Transport for all requests instead of the built in http client, see [`IconFetcher`].
+    /// `user_agent`, `user_agents`, `tcp_timeout`, `identity`, `http1_only`, `proxy`
+    /// and the pool settings only apply to the built in client.
+    pub fetcher: Option<Arc<dyn IconFetcher>>,
+    /// Start at most this many page and icon requests per second, spaced evenly.
+    /// The limit is shared by all sites of [`ImageLink::from_websites`] and works together
+    /// with `max_concurrency`, which bounds the requests in flight.
+    /// A value that is not positive means no limit.
+    pub max_requests_per_second: Option<f64>,
+    /// Drop icons whose file size, as reported by the server, is below this.
+    /// Tiny responses are error pages or placeholders rather than images.
+    /// The size comes from the headers of the probe, whose range is still downloaded,
+    /// the check keeps the bytes from being measured. Icons without a reported size are kept.
+    pub min_icon_bytes: u64,
+    /// Read the page in chunks and stop the download at the closing `</head>` tag.
+    /// Icons are almost always declared in the head, so big pages are not downloaded
+    /// completely. Icons that are only declared in the body are missed.
+    /// With another [`ExtractOptions::page_method`] than GET the page is downloaded
+    /// and only cut at the end of the head afterwards.
+    pub stop_at_head_end: bool,
+    /// Most requests in flight to any single host at the same time, shared by all
+    /// sites of a batch. Further requests to the host wait, requests to other hosts
+    /// continue. `None` or 0 only applies [`ExtractOptions::max_concurrency`].
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink};
+    ///
+    /// let options = ExtractOptions {
+    ///     max_concurrency: 16,
+    ///     max_per_host: Some(2),
+    ///     ..Default::default()
+    /// };
+    /// let urls = ["https://example.com/a", "https://example.com/b", "https://example.org/"];
+    /// let results = ImageLink::from_websites(&urls, &options).unwrap();
+    /// println!("{:?}", results);
+    /// ```
+    pub max_per_host: Option<usize>,
+    /// If the page can not be connected to, for example because its host does not
+    /// resolve, retry once with the `www.` prefix added to or removed from the host.
+    /// Icons are then resolved against the page that answered.
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink};
+    ///
+    /// let options = ExtractOptions {
+    ///     www_fallback: true,
+    ///     ..Default::default()
+    /// };
+    /// let result = ImageLink::from_website_detailed("https://example.com", &options).unwrap();
+    /// println!("{} answered", result.final_url);
+    /// ```
+    pub www_fallback: bool,
+    /// Accept page urls without a scheme like `example.com`. They are tried as
+    /// `https://` first and then as `http://`, the scheme that worked is the one of
+    /// [`ExtractionResult::requested_url`].
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink};
+    ///
+    /// let options = ExtractOptions {
+    ///     try_all_schemes: true,
+    ///     ..Default::default()
+    /// };
+    /// let result = ImageLink::from_website_detailed("example.com", &options).unwrap();
+    /// println!("answered over {}", result.requested_url.scheme());
+    /// ```
+    pub try_all_schemes: bool,
+    /// Only fetch icons on the host of the page, for scanning untrusted pages.
+    /// References that resolve to another host, like `//cdn.example.net/icon.png`,
+    /// are skipped with [`IconError::ForeignHost`]. Subdomains count as other hosts.
+    /// # Example
+    /// ```rust
+    /// use website_icon_extract::{ExtractOptions, IconError, ImageLink, Url};
+    ///
+    /// let options = ExtractOptions {
+    ///     same_host_only: true,
+    ///     ..Default::default()
+    /// };
+    /// let base = Url::parse("https://example.com/").unwrap();
+    /// let result = ImageLink::resolve_and_measure(&base, "//evil.example.net/x.png", &options);
+    /// assert!(matches!(result, Err(IconError::ForeignHost(host)) if host == "evil.example.net"));
+    /// ```
+    pub same_host_only: bool,
+    /// Proxy for the page and all icon requests, instead of the one configured
+    /// in the environment. SOCKS5 proxies need the `socks` feature. With the
+    /// `socks5h://` scheme host names are resolved by the proxy, which is required
+    /// for `.onion` addresses of Tor.
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink, Proxy};
+    ///
+    /// let options = ExtractOptions {
+    ///     proxy: Some(Proxy::all("socks5h://127.0.0.1:9050").unwrap()),
+    ///     tcp_timeout: 30,
+    ///     ..Default::default()
+    /// };
+    /// let url = "http://duckduckgogg42xjoc72x3sjasowoarfbgcmvfimaftt6twagswzczad.onion/";
+    /// let icons = ImageLink::from_website_with_options(url, &options).unwrap();
+    /// println!("{:?}", icons);
+    /// ```
+    pub proxy: Option<Proxy>,
+    /// Fail with [`IconError::PageStatus`] if the page answers with a status outside of 2xx.
+    /// By default the body of error pages is searched for icons like any other page.
+    pub require_success: bool,
+    /// Query parameters removed from icon urls before they are compared and fetched,
+    /// so cache busters do not make the same icon look like several.
+    /// A trailing `*` removes all parameters starting with the name.
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink};
+    ///
+    /// // `/icon.png?_=1681234567` and `/icon.png?utm_source=feed` are both fetched as `/icon.png`
+    /// let options = ExtractOptions {
+    ///     strip_query_params: vec![String::from("_"), String::from("utm_*")],
+    ///     ..Default::default()
+    /// };
+    /// let icons = ImageLink::from_website_with_options("https://example.com", &options).unwrap();
+    /// println!("{:?}", icons);
+    /// ```
+    pub strip_query_params: Vec<String>,
+    /// Remove the whole query of icon urls, see [`ExtractOptions::strip_query_params`]
+    pub strip_all_query: bool,
+    /// Http method for the page given by the caller, for pages that are only served
+    /// to POST requests. Icons, the sitemap and other pages are always fetched with GET.
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink, Method};
+    ///
+    /// let options = ExtractOptions {
+    ///     page_method: Method::POST,
+    ///     page_body: Some(b"q=icons".to_vec()),
+    ///     ..Default::default()
+    /// };
+    /// let icons = ImageLink::from_website_with_options("https://example.com/search", &options).unwrap();
+    /// println!("{:?}", icons);
+    /// ```
+    pub page_method: Method,
+    /// Body sent with the page request, see [`ExtractOptions::page_method`]
+    pub page_body: Option<Vec<u8>>,
+    /// If the page declares no icons but a `<link rel="canonical">` to another page,
+    /// extract from the canonical page. Only one hop is followed, relative references
+    /// are joined to the canonical url, which is reported as [`ExtractionResult::base_url`].
+    pub follow_canonical: bool,
+    /// Stop reading the page at the end of the head like [`ExtractOptions::stop_at_head_end`],
+    /// but also after this many bytes if the head does not end by then.
+    /// The part read so far is searched, so icons declared early are still found
+    /// in huge minified pages.
+    pub max_head_bytes: Option<usize>,
+    /// Log a warning for icons whose measured size is not the declared one,
+    /// see [`ImageLink::size_mismatch`]
+    pub warn_size_mismatch: bool,
+    /// Accept invalid TLS certificates for icon requests, for icons on a CDN with a broken
+    /// or expired certificate. The page itself and the sitemap are still only fetched over
+    /// validated connections. Without this such icons fail with [`IconError::Tls`].
+    /// Has no effect with a custom [`ExtractOptions::fetcher`].
+    pub accept_invalid_icon_certs: bool,
+    /// Probe an icon again with a four times larger range, up to this many times, when
+    /// its size could not be read from the downloaded bytes because they end too early.
+    /// This happens for jpeg files with big metadata before the size, or servers sending
+    /// less than requested. Network errors are not retried.
+    pub min_probe_retries_on_short_read: usize,
+    /// Most idle connections kept open per host by the built in client.
+    /// Batches over many hosts keep a connection to each of them, lowering this
+    /// saves file descriptors at the cost of new connections. `None` keeps all of them.
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink};
+    ///
+    /// let options = ExtractOptions {
+    ///     pool_max_idle_per_host: Some(1),
+    ///     pool_idle_timeout: Some(5),
+    ///     ..Default::default()
+    /// };
+    /// let results = ImageLink::from_websites(&["https://example.com", "https://example.org"], &options).unwrap();
+    /// println!("{:?}", results);
+    /// ```
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Seconds an idle connection of the built in client is kept open,
+    /// `None` for the default of the http client, 90 seconds
+    pub pool_idle_timeout: Option<u64>,
+    /// Most redirects the built in client follows for one request.
+    /// A redirect to an url that was already requested fails right away,
+    /// both with [`IconError::TooManyRedirects`].
+    pub max_redirects: usize,
+    /// Send `Accept-Encoding: identity` with the range requests of icon probes, so servers
+    /// send the requested bytes uncompressed. A compressed range can not be decompressed on its
+    /// own and costs another request without Range, reading the first bytes of the file.
+    /// The page is still fetched compressed.
+    /// Only applies to the built in client.
+    pub identity_encoding_for_probes: bool,
+    /// Probe icons with growing ranges of 32, 512 and 4096 bytes, none larger than this,
+    /// and stop at the first one that holds the size. Png and gif files are measured from
+    /// the first 32 bytes, jpeg files with metadata need more. This downloads fewer bytes
+    /// but takes another request for every larger range. `None` probes 262 bytes at once,
+    /// see [`ExtractOptions::min_probe_retries_on_short_read`] for reading more after that.
+    pub max_probe_bytes: Option<u64>,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        ExtractOptions {
+            user_agent: format!("website-icon-extract/{}", env!("CARGO_PKG_VERSION")),
+            tcp_timeout: 10,
+            identity: None,
+            dedup_by_content: false,
+            max_dimension: 16384,
+            icons_only: false,
+            exclude_ms_tiles: false,
+            favicon_location: FaviconLocation::Root,
+            http1_only: false,
+            max_html_bytes: 1024 * 1024,
+            parse: ParseOptions::default(),
+            upgrade_insecure: false,
+            sitemap_fallback: false,
+            allowed_types: None,
+            trust_declared_sizes: false,
+            max_concurrency: 4,
+            user_agents: Vec::new(),
+            on_skip: None,
+            max_candidates: None,
+            fetcher: None,
+            max_requests_per_second: None,
+            min_icon_bytes: 0,
+            stop_at_head_end: false,
+            max_per_host: None,
+            www_fallback: false,
+            try_all_schemes: false,
+            same_host_only: false,
+            proxy: None,
+            require_success: false,
+            strip_query_params: Vec::new(),
+            strip_all_query: false,
+            page_method: Method::GET,
+            page_body: None,
+            follow_canonical: false,
+            max_head_bytes: None,
+            warn_size_mismatch: false,
+            accept_invalid_icon_certs: false,
+            min_probe_retries_on_short_read: 0,
+            pool_max_idle_per_host: None,
+            pool_idle_timeout: None,
+            max_redirects: 10,
+            identity_encoding_for_probes: false,
+            max_probe_bytes: None,
+        }
+    }
+}
+
+impl ExtractOptions {
+    /// Options with the given user agent and timeout, everything else default
+    pub fn new<P: AsRef<str>>(user_agent: P, tcp_timeout: u64) -> Self {
+        ExtractOptions {
+            user_agent: user_agent.as_ref().to_string(),
+            tcp_timeout,
+            ..Default::default()
+        }
+    }
+
+    /// Build the http client used for all requests of one extraction
+    fn build_client(&self) -> Result<Client, reqwest::Error> {
+        self.client_builder().build()
+    }
+
+    /// Client for icon requests if it differs from the page client,
+    /// see [`ExtractOptions::accept_invalid_icon_certs`]
+    fn build_icon_client(&self) -> Result<Option<Client>, reqwest::Error> {
+        if !self.accept_invalid_icon_certs || self.fetcher.is_some() {
+            return Ok(None);
+        }
+        self.client_builder().danger_accept_invalid_certs(true).build().map(Some)
+    }
+
+    fn client_builder(&self) -> ClientBuilder {
+        let max_redirects = self.max_redirects;
+        let mut builder = Client::builder()
+            .timeout(Duration::new(self.tcp_timeout, 0))
+            .redirect(Policy::custom(move |attempt| {
+                if attempt.previous().contains(attempt.url()) {
+                    attempt.error("redirect loop")
+                } else if attempt.previous().len() > max_redirects {
+                    attempt.error("too many redirects")
+                } else {
+                    attempt.follow()
+                }
+            }))
+            .user_agent(&self.user_agent);
+        if let Some(identity) = &self.identity {
+            builder = builder.identity(identity.clone());
+        }
+        if self.http1_only {
+            builder = builder.http1_only();
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        if let Some(max_idle) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+        if let Some(idle_timeout) = self.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(Duration::new(idle_timeout, 0));
+        }
+        builder
+    }
+
+    /// Limiter for [`ExtractOptions::max_requests_per_second`]
+    fn rate_limiter(&self) -> Option<Arc<RateLimiter>> {
+        self.max_requests_per_second
+            .and_then(RateLimiter::new)
+            .map(Arc::new)
+    }
+
+    /// Limiter for [`ExtractOptions::max_per_host`]
+    fn host_limiter(&self) -> Option<Arc<HostLimiter>> {
+        self.max_per_host.and_then(HostLimiter::new).map(Arc::new)
+    }
+
+    /// Remove query parameters of an icon url as configured by
+    /// [`ExtractOptions::strip_query_params`] and [`ExtractOptions::strip_all_query`]
+    fn strip_query(&self, mut url: Url) -> Url {
+        if url.scheme() == "data" || url.query().is_none() {
+            return url;
+        }
+        if self.strip_all_query {
+            url.set_query(None);
+            return url;
+        }
+        let stripped = |name: &str| {
+            self.strip_query_params.iter().any(|param| match param.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == param,
+            })
+        };
+        // the kept parameters are written back as they were, re-encoding
+        // them would change the url of icons that are not cache busted
+        let query = url.query().unwrap_or_default().to_string();
+        let pieces: Vec<&str> = query.split('&').filter(|piece| !piece.is_empty()).collect();
+        let kept: Vec<&str> = pieces
+            .iter()
+            .copied()
+            .filter(|piece| {
+                let name = url::form_urlencoded::parse(piece.as_bytes()).next().map(|(name, _)| name);
+                !name.is_some_and(|name| stripped(&name))
+            })
+            .collect();
+        if kept.len() == pieces.len() {
+            return url;
+        }
+        if kept.is_empty() {
+            url.set_query(None);
+        } else {
+            url.set_query(Some(&kept.join("&")));
+        }
+        url
+    }
+
+    /// Check the icon against the page for [`ExtractOptions::same_host_only`].
+    /// Inline `data:` urls are always accepted.
+    fn check_host(&self, page_url: &Url, url: &Url) -> Result<(), IconError> {
+        if !self.same_host_only || url.scheme() == "data" || url.host() == page_url.host() {
+            return Ok(());
+        }
+        Err(IconError::ForeignHost(url.host_str().unwrap_or("").to_string()))
+    }
+
+    /// Check the format against [`ExtractOptions::allowed_types`]
+    fn check_type(&self, image_type: Option<ImageType>, description: &str) -> Result<(), IconError> {
+        match &self.allowed_types {
+            Some(allowed) if !image_type.is_some_and(|t| allowed.contains(&t)) => {
+                Err(IconError::DisallowedType(description.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check if icons from this source should be fetched at all
+    fn wants_source(&self, source: IconSource) -> bool {
+        match source {
+            IconSource::OpenGraph | IconSource::Social | IconSource::Picture => !self.icons_only,
+            IconSource::MsTile => !self.exclude_ms_tiles,
+            _ => true,
+        }
+    }
+}
+
+/// Wrap the fetcher to wait for the limiter before every request
+fn rate_limited(fetcher: Arc<dyn IconFetcher>, limiter: Option<Arc<RateLimiter>>) -> Arc<dyn IconFetcher> {
+    match limiter {
+        Some(limiter) => Arc::new(RateLimitedFetcher {
+            inner: fetcher,
+            limiter,
+        }),
+        None => fetcher,
+    }
+}
+
+/// Wrap the fetcher to wait for a free slot of the host before every request
+fn host_limited(fetcher: Arc<dyn IconFetcher>, limiter: Option<Arc<HostLimiter>>) -> Arc<dyn IconFetcher> {
+    match limiter {
+        Some(limiter) => Arc::new(HostLimitedFetcher {
+            inner: fetcher,
+            limiter,
+        }),
+        None => fetcher,
+    }
+}
+
+/// Fetcher and counters shared by all requests of one extraction
+struct Session<'a> {
+    fetcher: Arc<dyn IconFetcher>,
+    /// Fetcher for icon probes, the page fetcher unless icons use another client
+    icon_fetcher: Arc<dyn IconFetcher>,
+    options: &'a ExtractOptions,
+    /// Bytes downloaded by icon probes, including failed ones
+    icon_bytes: AtomicUsize,
+    /// Number of threads for probing icons
+    workers: usize,
+    /// Icons measured by earlier sites of a batch, by url
+    cache: Option<&'a Mutex<HashMap<Url, ImageLink>>>,
+}
+
+impl<'a> Session<'a> {
+    fn new(options: &'a ExtractOptions) -> Result<Self, IconError> {
+        let fetcher: Arc<dyn IconFetcher> = match &options.fetcher {
+            Some(fetcher) => fetcher.clone(),
+            None => Arc::new(ReqwestFetcher {
+                client: options.build_client()?,
+                user_agent: None,
+                identity_ranges: options.identity_encoding_for_probes,
+            }),
+        };
+        let limiter = options.rate_limiter();
+        let host_limiter = options.host_limiter();
+        let icon_client = options.build_icon_client()?;
+        let mut session = Session::with_fetcher(
+            host_limited(rate_limited(fetcher, limiter.clone()), host_limiter.clone()),
+            options,
+            options.max_concurrency,
+        );
+        session.use_icon_client(icon_client, None, limiter, host_limiter);
+        Ok(session)
+    }
+
+    /// Probe icons with their own client, limited like the page requests
+    fn use_icon_client(
+        &mut self,
+        client: Option<Client>,
+        user_agent: Option<String>,
+        limiter: Option<Arc<RateLimiter>>,
+        host_limiter: Option<Arc<HostLimiter>>,
+    ) {
+        if let Some(client) = client {
+            let fetcher: Arc<dyn IconFetcher> = Arc::new(ReqwestFetcher {
+                client,
+                user_agent,
+                identity_ranges: self.options.identity_encoding_for_probes,
+            });
+            self.icon_fetcher = host_limited(rate_limited(fetcher, limiter), host_limiter);
+        }
+    }
+
+    /// Icon measured earlier in the batch, described by the new candidate
+    fn cached(&self, candidate: &Candidate) -> Option<ImageLink> {
+        let cache = self.cache?.lock().ok()?;
+        let mut icon = cache.get(&candidate.url)?.clone();
+        icon.source = candidate.source;
+        icon.declared_size = candidate.declared_size;
+        icon.declared_sizes = candidate.declared_sizes.clone();
+        icon.elapsed = None;
+        icon.from_cache = true;
+        Some(icon.checked_against_declaration(self.options))
+    }
+
+    /// Fetch a html page, only up to the end of its head if the options say so
+    fn fetch_html(&self, url: &Url) -> Result<FetchedResponse, IconError> {
+        if let Some(max_head_bytes) = self.options.max_head_bytes {
+            self.fetcher.fetch_head(url, max_head_bytes.min(self.options.max_html_bytes))
+        } else if self.options.stop_at_head_end {
+            self.fetcher.fetch_head(url, self.options.max_html_bytes)
+        } else {
+            self.fetcher.fetch_page(url, self.options.max_html_bytes)
+        }
+    }
+
+    /// Fetch the page given by the caller, with the method and body of the options.
+    /// Other requests than a plain GET can not stop at the end of the head,
+    /// they are cut there after the download instead.
+    fn fetch_start_page(&self, url: &Url) -> Result<FetchedResponse, IconError> {
+        let options = self.options;
+        if options.page_method == Method::GET && options.page_body.is_none() {
+            return self.fetch_html(url);
+        }
+        let limit = match options.max_head_bytes {
+            Some(max_head_bytes) => max_head_bytes.min(options.max_html_bytes),
+            None => options.max_html_bytes,
+        };
+        let mut response =
+            self.fetcher.fetch_page_with(url, limit, &options.page_method, options.page_body.as_deref())?;
+        if options.stop_at_head_end || options.max_head_bytes.is_some() {
+            if let Some(end) = parse::head_end(&response.body, 0) {
+                response.body.truncate(end);
+            }
+        }
+        Ok(response)
+    }
+
+    fn with_fetcher(fetcher: Arc<dyn IconFetcher>, options: &'a ExtractOptions, workers: usize) -> Self {
+        Session {
+            icon_fetcher: fetcher.clone(),
+            fetcher,
+            options,
+            icon_bytes: AtomicUsize::new(0),
+            workers,
+            cache: None,
+        }
+    }
+}
+
+/// Response to the page request, to find out why a page yielded no icons
+#[derive(Clone, Debug)]
+pub struct PageResponse {
+    /// Http status code, for example 403 for pages blocking bots
+    pub status: u16,
+    /// Content-Type header, only html pages are analyzed
+    pub content_type: Option<String>,
+    /// Content-Length header
+    pub content_length: Option<u64>,
+    /// Server header
+    pub server: Option<String>,
+}
+
+/// Detailed outcome of extracting the icons of a website
+#[derive(Debug)]
+pub struct ExtractionResult {
+    /// Url of the page as given by the caller
+    pub requested_url: Url,
+    /// Url of the page after following all redirects, the html was read from here
+    pub final_url: Url,
+    /// Url of the page the icons were declared in, differs from `final_url`
+    /// if another page was used, see [`ExtractionAudit::base_url`]
+    pub base_url: Url,
+    /// Status and headers of the page response
+    pub page: PageResponse,
+    /// Icons that could be measured, in the order described at
+    /// [`ImageLink::from_website_with_options`]
+    pub icons: Vec<ImageLink>,
+    /// Bytes read from the html page
+    pub html_bytes: usize,
+    /// Bytes downloaded by all icon probes, including the ones that failed.
+    /// Probes stop reading after 262 bytes, also from servers that ignore the range request.
+    pub icon_bytes_total: usize,
+    /// Candidates that were dropped, with the reason
+    pub errors: Vec<(Url, IconError)>,
+    /// References exactly as written in the page, in document order,
+    /// before they were joined to the page url or filtered
+    pub raw_refs: Vec<String>,
+    /// Title and names of the page, empty unless [`ParseOptions::page_metadata`] is set
+    pub metadata: PageMetadata,
+    /// The site has no icon: the page declares none that could be fetched and the
+    /// default favicon does not exist. A placeholder is the right fallback.
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink};
+    ///
+    /// let result = ImageLink::from_website_detailed("https://example.com", &ExtractOptions::default()).unwrap();
+    /// if result.no_refs_found {
+    ///     println!("no icon, using a placeholder");
+    /// } else if result.all_probes_failed {
+    ///     println!("no icon could be loaded, retrying later");
+    /// }
+    /// ```
+    pub no_refs_found: bool,
+    /// No icon could be measured although there was something to probe, either
+    /// declared icons or a default favicon that failed with a network error.
+    /// Retrying later may help.
+    pub all_probes_failed: bool,
+    /// Time the whole extraction took, page and icons
+    pub elapsed: Duration,
+}
+
+/// Complete report of one extraction, see [`ImageLink::audit`]
+#[derive(Debug)]
+pub struct ExtractionAudit {
+    /// Url of the page as given by the caller
+    pub requested_url: Url,
+    /// Url of the page after following all redirects
+    pub final_url: Url,
+    /// Url relative references were joined to: the final url, the canonical page with
+    /// [`ExtractOptions::follow_canonical`] or a page from the sitemap with
+    /// [`ExtractOptions::sitemap_fallback`]
+    pub base_url: Url,
+    /// Status and headers of the page response
+    pub page: PageResponse,
+    /// Every reference found in the page followed by the guessed default favicon,
+    /// in the order they were considered
+    pub entries: Vec<AuditEntry>,
+    /// Bytes read from the html page
+    pub html_bytes: usize,
+    /// Bytes downloaded by all icon probes, including the ones that failed
+    pub icon_bytes_total: usize,
+    /// Title and names of the page, empty unless [`ParseOptions::page_metadata`] is set
+    pub metadata: PageMetadata,
+    /// Time the whole extraction took, page and icons
+    pub elapsed: Duration,
+}
+
+/// One icon reference of an [`ExtractionAudit`]
+#[derive(Debug)]
+pub struct AuditEntry {
+    /// Reference as written in the page, `None` for the guessed default favicon
+    pub href: Option<String>,
+    /// Kind of declaration the reference was found in
+    pub source: IconSource,
+    /// Resolved url, `None` if the reference could not be resolved
+    pub url: Option<Url>,
+    /// What happened to the reference
+    pub outcome: AuditOutcome,
+}
+
+/// What happened to an icon reference during an extraction
+#[derive(Debug)]
+// most entries of an audit are kept icons, boxing them would not save memory
+#[allow(clippy::large_enum_variant)]
+pub enum AuditOutcome {
+    /// Measured and part of the result
+    Kept(ImageLink),
+    /// Not fetched because the options exclude icons from this source
+    Excluded,
+    /// Not fetched because the reference is empty, is an unfilled template,
+    /// can not be joined, has an unsupported scheme or is on another host
+    Filtered(IconError),
+    /// Not fetched because an earlier reference has the same url
+    DuplicateUrl,
+    /// Not fetched because of [`ExtractOptions::max_candidates`]
+    OverLimit,
+    /// Fetching or measuring failed
+    Failed(IconError),
+    /// Same content as the icon kept at this index of the entries,
+    /// see [`ExtractOptions::dedup_by_content`]
+    DuplicateContent(usize),
+}
+
+impl From<ExtractionAudit> for ExtractionResult {
+    fn from(audit: ExtractionAudit) -> Self {
+        let declared_failed = audit
+            .entries
+            .iter()
+            .any(|entry| entry.href.is_some() && matches!(entry.outcome, AuditOutcome::Failed(_)));
+        let favicon_unreachable = audit.entries.iter().any(|entry| {
+            entry.source == IconSource::DefaultFavicon
+                && matches!(
+                    entry.outcome,
+                    AuditOutcome::Failed(IconError::Http(_))
+                        | AuditOutcome::Failed(IconError::Tls(_))
+                        | AuditOutcome::Failed(IconError::Io(_))
+                )
+        });
+        let raw_refs: Vec<String> = audit.entries.iter().filter_map(|entry| entry.href.clone()).collect();
+        let mut icons: Vec<ImageLink> = Vec::new();
+        let mut errors: Vec<(Url, IconError)> = Vec::new();
+        for entry in audit.entries {
+            match (entry.outcome, entry.url) {
+                (AuditOutcome::Kept(icon), _) => icons.push(icon),
+                (AuditOutcome::Filtered(error), Some(url)) | (AuditOutcome::Failed(error), Some(url)) => {
+                    errors.push((url, error))
+                }
+                _ => {}
+            }
+        }
+        let icons_empty = icons.is_empty();
+        ExtractionResult {
+            requested_url: audit.requested_url,
+            final_url: audit.final_url,
+            base_url: audit.base_url,
+            page: audit.page,
+            icons,
+            html_bytes: audit.html_bytes,
+            icon_bytes_total: audit.icon_bytes_total,
+            errors,
+            raw_refs,
+            metadata: audit.metadata,
+            no_refs_found: icons_empty && !declared_failed && !favicon_unreachable,
+            all_probes_failed: icons_empty && (declared_failed || favicon_unreachable),
+            elapsed: audit.elapsed,
+        }
+    }
+}
+
+/// Holds information about an image
+#[derive(Clone, Debug)]
 pub struct ImageLink {
     /// Url to image
     pub url: Url,
-    /// Type of image
-    pub image_type: ImageType,
+    /// Type of image, sniffed from the downloaded bytes. The Content-Type header
+    /// is not trusted, it is kept in `content_type`.
+    /// `None` for vector images, see `scalable`.
+    pub image_type: Option<ImageType>,
     /// Pixel width of image
     pub width: usize,
     /// Pixel height of image
     pub height: usize,
+    /// Hash of the downloaded bytes, equal for icons with identical content.
+    /// Only the probed start of the file is hashed, `None` if that start is too short
+    /// to tell files apart, like the first bytes of a progressive probe.
+    pub content_hash: Option<u64>,
+    /// Content-Type header as sent by the server, may disagree with `image_type`
+    pub content_type: Option<String>,
+    /// Kind of declaration the icon was found in
+    pub source: IconSource,
+    /// Vector image that can be rendered at any size.
+    /// Width and height are the intrinsic size, 0 if the image does not declare one.
+    /// Svg icons declared with `sizes="any"` are taken as scalable without downloading them,
+    /// unless [`ExtractOptions::allowed_types`] is set.
+    pub scalable: bool,
+    /// Time the probe request took including the body download,
+    /// `None` if no request was made
+    pub elapsed: Option<Duration>,
+    /// Size declared by the `sizes` attribute of the link, may differ from the measured size.
+    /// Apple touch icons often leave it out, devices then assume their own default size,
+    /// so it is `None` and callers can apply their own convention.
+    /// If the attribute lists several sizes, this is the largest of them.
+    pub declared_size: Option<(usize, usize)>,
+    /// All sizes listed by the `sizes` attribute in their order, like the sizes
+    /// `16x16 32x32 48x48` contained in one ico file
+    pub declared_sizes: Vec<(usize, usize)>,
+    /// Width and height were measured from the image data
+    /// and not taken from the declaration
+    pub measured: bool,
+    /// Size of the complete file in bytes, if the server reported it
+    pub file_size: Option<u64>,
+    /// Measured for an earlier site of the same [`ImageLink::from_websites`] batch
+    /// and not fetched again
+    pub from_cache: bool,
+    /// The measured size is none of the sizes declared by the `sizes` attribute,
+    /// like a `sizes="32x32"` link to a 16x16 image.
+    /// See [`ExtractOptions::warn_size_mismatch`] to log these.
+    pub size_mismatch: bool,
+    /// Pages the icon was found for, in the order they were given.
+    /// Only filled by [`ImageLink::from_pages`], empty otherwise.
+    pub pages: Vec<Url>,
+    /// Sizes of all images in an ico file in the order of its directory, empty for other formats.
+    /// The directory is read from the probed bytes, which hold up to 16 entries.
+    /// Files with more only report the first 16 and `width` and `height` are the largest of them,
+    /// unless [`ExtractOptions::min_probe_retries_on_short_read`] allows reading on.
+    pub contained_sizes: Vec<(usize, usize)>,
+    /// The server answered the probe with `206 Partial Content`, so only the start of the file
+    /// was sent. False if it ignored the range and sent the whole file, of which only the start
+    /// was read, and for icons that were not downloaded.
+    pub partial: bool,
 }
 
 impl ImageLink {
-    pub fn new<U: IntoUrl, P: AsRef<str>>(
+    /// Measure the image at an url by downloading its first 262 bytes.
+    /// Servers that compress the requested range can not be read that way,
+    /// the image is then refetched without Range, reading its first 262 bytes.
+    /// # Arguments
+    /// * `url` - Url of the image
+    /// * `user_agent` - User agent header string for http requests
+    /// * `tcp_timeout` - Http timeout in seconds
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::ImageLink;
+    ///
+    /// let icon = ImageLink::new("https://example.com/favicon.ico", "TEST", 5).unwrap();
+    /// println!("{}x{}", icon.width, icon.height);
+    /// ```
+    pub fn new<U: AsRef<str>, P: AsRef<str>>(
         url: U,
         user_agent: P,
         tcp_timeout: u64,
-    ) -> Result<Self, Box<dyn Error>> {
-        let url = url.into_url()?;
-        let (image_size, image_type) = get_pixel_size(url.clone(), user_agent, tcp_timeout)?;
+    ) -> Result<Self, IconError> {
+        ImageLink::new_with_options(url, &ExtractOptions::new(user_agent, tcp_timeout))
+    }
+
+    /// Same as [`ImageLink::new`] but with all [`ExtractOptions`] available.
+    /// Inline `data:` urls are decoded and measured without any request.
+    /// # Example
+    /// ```rust
+    /// use website_icon_extract::{ExtractOptions, IconError, ImageLink};
+    ///
+    /// let url = "data:image/svg+xml,%3Csvg%20viewBox='0%200%2064%2032'%3E%3C/svg%3E";
+    /// let icon = ImageLink::new_with_options(url, &ExtractOptions::default()).unwrap();
+    /// assert!(icon.scalable);
+    /// assert_eq!((icon.width, icon.height), (64, 32));
+    ///
+    /// let result = ImageLink::new_with_options("chrome://branding/icon.png", &ExtractOptions::default());
+    /// assert!(matches!(result, Err(IconError::UnsupportedScheme(scheme)) if scheme == "chrome"));
+    /// ```
+    pub fn new_with_options<U: AsRef<str>>(
+        url: U,
+        options: &ExtractOptions,
+    ) -> Result<Self, IconError> {
+        let session = Session::new(options)?;
+        let candidate = Candidate {
+            url: Url::parse(url.as_ref())?,
+            source: IconSource::Direct,
+            declared_size: None,
+            declared_sizes: Vec::new(),
+            declared_type: None,
+            declared_any: false,
+        };
+        ImageLink::probe(&session, candidate)
+    }
+
+    fn probe(session: &Session, candidate: Candidate) -> Result<Self, IconError> {
+        let options = session.options;
+        check_scheme(&candidate.url)?;
+        if let Some(icon) = ImageLink::from_declaration(&candidate, options) {
+            return Ok(icon);
+        }
+        let Candidate {
+            url,
+            source,
+            declared_size,
+            declared_sizes,
+            declared_type,
+            ..
+        } = candidate;
+        if let Some(declared_type) = declared_type {
+            // the page already told the format, no need to download a disallowed one
+            options.check_type(Some(declared_type), &format!("{:?}", declared_type))?;
+        }
+        let mut probe = if url.scheme() == "data" {
+            decode_data_image(&url)?
+        } else {
+            get_pixel_size(session, url.clone())?
+        };
+        probe.image_type = probe.image_type.or(declared_type);
+        let description = match (probe.image_type, &probe.content_type) {
+            (Some(image_type), _) => format!("{:?}", image_type),
+            (None, Some(content_type)) => content_type.clone(),
+            (None, None) => String::from("unknown"),
+        };
+        options.check_type(probe.image_type, &description)?;
+        if probe.size.width > options.max_dimension || probe.size.height > options.max_dimension {
+            return Err(IconError::ImplausibleSize {
+                width: probe.size.width,
+                height: probe.size.height,
+            });
+        }
         Ok(ImageLink {
             url,
-            image_type,
-            width: image_size.width,
-            height: image_size.height,
+            image_type: probe.image_type,
+            width: probe.size.width,
+            height: probe.size.height,
+            content_hash: probe.content_hash,
+            content_type: probe.content_type,
+            source,
+            scalable: probe.scalable,
+            elapsed: probe.elapsed,
+            declared_size,
+            declared_sizes,
+            measured: true,
+            file_size: probe.file_size,
+            from_cache: false,
+            size_mismatch: false,
+            pages: Vec::new(),
+            contained_sizes: probe.contained_sizes,
+            partial: probe.partial,
+        }
+        .checked_against_declaration(options))
+    }
+
+    /// Set [`ImageLink::size_mismatch`], logging a mismatch if the options ask for it
+    fn checked_against_declaration(mut self, options: &ExtractOptions) -> Self {
+        self.size_mismatch = self.measured
+            && !self.scalable
+            && !self.declared_sizes.is_empty()
+            && !self.declared_sizes.contains(&(self.width, self.height));
+        if self.size_mismatch && options.warn_size_mismatch {
+            warn!(
+                "{}, declared size {:?} but measured {}x{}",
+                self.url, self.declared_sizes, self.width, self.height
+            );
+        }
+        self
+    }
+
+    /// Icon built only from its declaration, if probing it can be skipped.
+    /// `sizes="any"` on a svg declares a scalable icon, the sizes of apple touch icons
+    /// are only trusted if the options say so.
+    fn from_declaration(candidate: &Candidate, options: &ExtractOptions) -> Option<Self> {
+        if options.allowed_types.is_some() {
+            return None;
+        }
+        let (width, height, scalable) = if candidate.declared_any {
+            (0, 0, true)
+        } else if options.trust_declared_sizes && candidate.source == IconSource::AppleTouch {
+            let (width, height) = candidate.declared_size?;
+            if width > options.max_dimension || height > options.max_dimension {
+                return None;
+            }
+            (width, height, false)
+        } else {
+            return None;
+        };
+        Some(ImageLink {
+            url: candidate.url.clone(),
+            image_type: None,
+            width,
+            height,
+            content_hash: None,
+            content_type: None,
+            source: candidate.source,
+            scalable,
+            elapsed: None,
+            declared_size: candidate.declared_size,
+            declared_sizes: candidate.declared_sizes.clone(),
+            measured: false,
+            file_size: None,
+            from_cache: false,
+            size_mismatch: false,
+            pages: Vec::new(),
+            contained_sizes: Vec::new(),
+            partial: false,
         })
     }
 
+    /// Probe a `http://` candidate over `https://` first, then as it was declared
+    fn probe_upgraded(session: &Session, candidate: Candidate) -> Result<Self, IconError> {
+        if candidate.url.scheme() == "http" {
+            let mut upgraded = candidate.clone();
+            if upgraded.url.set_scheme("https").is_ok() {
+                if let Ok(icon) = ImageLink::probe(session, upgraded) {
+                    return Ok(icon);
+                }
+            }
+        }
+        ImageLink::probe(session, candidate)
+    }
+
+    /// Resolve an icon reference found by some other parser and measure it,
+    /// the same way declared icons of a page are handled
+    /// # Arguments
+    /// * `base` - Url of the page the reference was found in
+    /// * `href` - Icon reference as written in the page
+    /// * `options` - Options for the request
+    /// # Example
+    /// ```rust
+    /// use website_icon_extract::{ExtractOptions, IconError, ImageLink, Url};
+    ///
+    /// let base = Url::parse("https://example.com/app/").unwrap();
+    /// let options = ExtractOptions::default();
+    /// let href = "data:image/svg+xml,%3Csvg%20width='24'%20height='24'%3E%3C/svg%3E";
+    /// let icon = ImageLink::resolve_and_measure(&base, href, &options).unwrap();
+    /// assert_eq!((icon.width, icon.height), (24, 24));
+    /// let result = ImageLink::resolve_and_measure(&base, " ", &options);
+    /// assert!(matches!(result, Err(IconError::EmptyHref)));
+    /// ```
+    pub fn resolve_and_measure(
+        base: &Url,
+        href: &str,
+        options: &ExtractOptions,
+    ) -> Result<ImageLink, IconError> {
+        let url = options.strip_query(resolve_href(base, href)?);
+        options.check_host(base, &url)?;
+        let session = Session::new(options)?;
+        let candidate = Candidate {
+            url,
+            source: IconSource::Direct,
+            declared_size: None,
+            declared_sizes: Vec::new(),
+            declared_type: None,
+            declared_any: false,
+        };
+        if options.upgrade_insecure && base.scheme() == "https" {
+            ImageLink::probe_upgraded(&session, candidate)
+        } else {
+            ImageLink::probe(&session, candidate)
+        }
+    }
+
+    /// Check that the url still serves a valid image, with the same small range
+    /// request used for measuring. If it does, the measured fields are updated,
+    /// so changed dimensions are picked up, and the source, declared size and pages are kept.
+    /// # Returns
+    /// `false` if the url answers but no longer with an acceptable image,
+    /// errors only if the request itself failed
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink};
+    ///
+    /// let options = ExtractOptions::default();
+    /// let mut icon = ImageLink::new_with_options("https://example.com/icon.png", &options).unwrap();
+    /// // some time later
+    /// if !icon.revalidate(&options).unwrap() {
+    ///     println!("{} is gone", icon.url);
+    /// }
+    /// ```
+    pub fn revalidate(&mut self, options: &ExtractOptions) -> Result<bool, IconError> {
+        let session = Session::new(options)?;
+        let candidate = Candidate {
+            url: self.url.clone(),
+            source: self.source,
+            declared_size: None,
+            declared_sizes: Vec::new(),
+            declared_type: None,
+            declared_any: false,
+        };
+        match ImageLink::probe(&session, candidate) {
+            Ok(icon) => {
+                *self = ImageLink {
+                    declared_size: self.declared_size,
+                    declared_sizes: std::mem::take(&mut self.declared_sizes),
+                    pages: std::mem::take(&mut self.pages),
+                    ..icon
+                }
+                .checked_against_declaration(options);
+                Ok(true)
+            }
+            Err(err @ IconError::Http(_)) | Err(err @ IconError::Tls(_)) | Err(err @ IconError::Io(_)) => Err(err),
+            Err(err) => {
+                trace!("{}, no longer valid: {}", self.url, err);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Probe only the default `/favicon.ico` of a host, without downloading any html.
+    /// `https` is tried first, then `http`.
+    /// # Arguments
+    /// * `host_or_url` - A bare host like `example.com` or any url on the host
+    /// * `options` - Options for the requests
+    /// # Returns
+    /// `None` if neither location serves an image
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink};
+    ///
+    /// let icon = ImageLink::favicon_for_host("example.com", &ExtractOptions::default()).unwrap();
+    /// println!("{:?}", icon);
+    /// ```
+    ///
+    /// Ip addresses work as hosts too, IPv6 literals are written in brackets:
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink};
+    ///
+    /// let icon = ImageLink::favicon_for_host("[::1]:8080", &ExtractOptions::default()).unwrap();
+    /// println!("{:?}", icon);
+    /// ```
+    pub fn favicon_for_host<P: AsRef<str>>(
+        host_or_url: P,
+        options: &ExtractOptions,
+    ) -> Result<Option<ImageLink>, IconError> {
+        let input = host_or_url.as_ref().trim();
+        let authority = if input.contains("://") {
+            let url = Url::parse(input)?;
+            let host = url.host_str().ok_or(url::ParseError::EmptyHost)?;
+            match url.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host.to_string(),
+            }
+        } else {
+            input.split('/').next().unwrap_or("").to_string()
+        };
+        let session = Session::new(options)?;
+        for scheme in &["https", "http"] {
+            let candidate = Candidate {
+                url: Url::parse(&format!("{}://{}/favicon.ico", scheme, authority))?,
+                source: IconSource::DefaultFavicon,
+                declared_size: None,
+                declared_sizes: Vec::new(),
+                declared_type: None,
+                declared_any: false,
+            };
+            if let Ok(icon) = ImageLink::probe(&session, candidate) {
+                return Ok(Some(icon));
+            }
+        }
+        Ok(None)
+    }
+
     /// Extracts information about icons from website by:
     /// * Download and analyze a html page from http/https url.
     /// * Return all found icon urls.
-    /// * Check their sizes by downloading the first 100 bytes
+    /// * Check their sizes by downloading the first 262 bytes
+    ///
+    /// Relative icon references are joined to the page url after redirects like a browser does,
+    /// so for `https://example.com/app` the reference `icon.png` resolves to
+    /// `https://example.com/icon.png` while for `https://example.com/app/` it resolves to
+    /// `https://example.com/app/icon.png`. The undeclared default favicon is only tried
+    /// at the site root, see [`ExtractOptions::favicon_location`] to change that.
     /// # Arguments
     /// * `url` - An url to check
     /// * `user_agent` - User agent header string for http requests
     /// * `tcp_timeout` - Http timeout in seconds
     /// # Example
-    /// ```rust
+    /// ```rust,no_run
+    /// use website_icon_extract::ImageLink;
+    ///
     /// let url = "https://google.com";
     /// let list = ImageLink::from_website(url, "TEST", 5).unwrap();
-    /// println("{:?}", list);
+    /// println!("{:?}", list);
     /// ```
     pub fn from_website<P, Q>(
         base_url: P,
         user_agent: Q,
         tcp_timeout: u64,
-    ) -> Result<Vec<ImageLink>, Box<dyn Error>>
+    ) -> Result<Vec<ImageLink>, IconError>
     where
         P: AsRef<str>,
         Q: AsRef<str>,
     {
-        let base_url = Url::parse(base_url.as_ref())?;
-        let response = Client::new()
-            .get(base_url.clone())
-            .timeout(Duration::new(tcp_timeout, 0))
-            .header(USER_AGENT, user_agent.as_ref())
-            .send()?;
-    
-        let mut list: Vec<String> = analyze_location(response)?;
-        list.push(String::from("/favicon.ico"));
-        Ok(list
+        ImageLink::from_website_with_options(base_url, &ExtractOptions::new(user_agent, tcp_timeout))
+    }
+
+    /// Same as [`ImageLink::from_website`] but with all [`ExtractOptions`] available.
+    ///
+    /// The order of the result is stable: icons declared in the page come first in
+    /// source order, followed by the guessed default favicon.
+    /// Use [`sort_by_size`] to order by pixel size instead.
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, Identity, ImageLink};
+    ///
+    /// let pem = std::fs::read("client.pem").unwrap();
+    /// let key = std::fs::read("client.key").unwrap();
+    /// let options = ExtractOptions {
+    ///     identity: Some(Identity::from_pkcs8_pem(&pem, &key).unwrap()),
+    ///     ..Default::default()
+    /// };
+    /// let list = ImageLink::from_website_with_options("https://internal.example", &options).unwrap();
+    /// println!("{:?}", list);
+    /// ```
+    pub fn from_website_with_options<P>(
+        base_url: P,
+        options: &ExtractOptions,
+    ) -> Result<Vec<ImageLink>, IconError>
+    where
+        P: AsRef<str>,
+    {
+        Ok(ImageLink::from_website_detailed(base_url, options)?.icons)
+    }
+
+    /// Same as [`ImageLink::from_website_with_options`] with the icons sorted by `compare`.
+    /// The sort is stable, icons that compare equal stay in document order.
+    /// # Arguments
+    /// * `base_url` - An url to check
+    /// * `options` - Options for the requests
+    /// * `compare` - Ordering of two icons, the first icon of the result is the smallest
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink, ImageType};
+    ///
+    /// // png icons first, then by area from large to small
+    /// let icons = ImageLink::from_website_sorted_by("https://example.com", &ExtractOptions::default(), |a, b| {
+    ///     let png = |icon: &ImageLink| icon.image_type != Some(ImageType::Png);
+    ///     png(a)
+    ///         .cmp(&png(b))
+    ///         .then((b.width * b.height).cmp(&(a.width * a.height)))
+    /// })
+    /// .unwrap();
+    /// println!("{:?}", icons.first());
+    /// ```
+    pub fn from_website_sorted_by<P, F>(
+        base_url: P,
+        options: &ExtractOptions,
+        compare: F,
+    ) -> Result<Vec<ImageLink>, IconError>
+    where
+        P: AsRef<str>,
+        F: Fn(&ImageLink, &ImageLink) -> std::cmp::Ordering,
+    {
+        let mut icons = ImageLink::from_website_with_options(base_url, options)?;
+        icons.sort_by(compare);
+        Ok(icons)
+    }
+
+    /// Extract the icons of several pages of one site and merge them into one list.
+    /// Pages of a site do not always declare the same icons. Every icon appears once,
+    /// at the position it was first found, and [`ImageLink::pages`] lists the pages
+    /// it was found for. Icons found for several pages are only fetched once.
+    /// Pages that fail are left out, the error of the last page is returned if all fail.
+    /// # Arguments
+    /// * `urls` - Pages to extract from
+    /// * `options` - Options for the requests
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink, Url};
+    ///
+    /// let pages = [Url::parse("https://example.com/").unwrap(), Url::parse("https://example.com/about").unwrap()];
+    /// for icon in ImageLink::from_pages(&pages, &ExtractOptions::default()).unwrap() {
+    ///     println!("{} found on {:?}", icon.url, icon.pages);
+    /// }
+    /// ```
+    pub fn from_pages(urls: &[Url], options: &ExtractOptions) -> Result<Vec<ImageLink>, IconError> {
+        let cache: Mutex<HashMap<Url, ImageLink>> = Mutex::new(HashMap::new());
+        let mut session = Session::new(options)?;
+        session.cache = Some(&cache);
+        let mut merged: Vec<ImageLink> = Vec::new();
+        let mut last_error = None;
+        let mut any_page = false;
+        for page in urls {
+            let result = match ImageLink::extract_with(&session, vec![page.clone()]) {
+                Ok(result) => result,
+                Err(err) => {
+                    trace!("{}, skipping page: {}", page, err);
+                    last_error = Some(err);
+                    continue;
+                }
+            };
+            any_page = true;
+            for icon in result.icons {
+                match merged.iter_mut().find(|other| other.url == icon.url) {
+                    Some(other) => other.pages.push(page.clone()),
+                    None => merged.push(ImageLink {
+                        pages: vec![page.clone()],
+                        from_cache: false,
+                        ..icon
+                    }),
+                }
+            }
+        }
+        match last_error {
+            Some(err) if !any_page => Err(err),
+            _ => Ok(merged),
+        }
+    }
+
+    /// Same as [`ImageLink::from_website_with_options`] with the icons indexed by `(width, height)`.
+    /// If several icons have the same size, icons declared by the page win over the
+    /// undeclared default favicon, then png wins over other formats, then the first in document order.
+    /// # Arguments
+    /// * `base_url` - An url to check
+    /// * `options` - Options for the requests
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink};
+    ///
+    /// let icons = ImageLink::from_website_by_size("https://example.com", &ExtractOptions::default()).unwrap();
+    /// if let Some(icon) = icons.get(&(32, 32)) {
+    ///     println!("32x32 icon at {}", icon.url);
+    /// }
+    /// ```
+    pub fn from_website_by_size<P>(
+        base_url: P,
+        options: &ExtractOptions,
+    ) -> Result<BTreeMap<(usize, usize), ImageLink>, IconError>
+    where
+        P: AsRef<str>,
+    {
+        // declared before default favicon, then png before other formats
+        let rank = |icon: &ImageLink| {
+            (
+                icon.source != IconSource::DefaultFavicon,
+                icon.image_type == Some(ImageType::Png),
+            )
+        };
+        let mut by_size: BTreeMap<(usize, usize), ImageLink> = BTreeMap::new();
+        for icon in ImageLink::from_website_with_options(base_url, options)? {
+            let size = (icon.width, icon.height);
+            match by_size.get(&size) {
+                Some(existing) if rank(existing) >= rank(&icon) => {}
+                _ => {
+                    by_size.insert(size, icon);
+                }
+            }
+        }
+        Ok(by_size)
+    }
+
+    /// Same as [`ImageLink::from_website_with_options`] but also reports the final
+    /// page url after redirects, why candidates were dropped, how many bytes were
+    /// downloaded for the page and the icons and how long it took
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink};
+    ///
+    /// let result = ImageLink::from_website_detailed("https://google.com", &ExtractOptions::default()).unwrap();
+    /// println!("{} icons, {} bytes", result.icons.len(), result.html_bytes + result.icon_bytes_total);
+    /// println!("page answered {} {:?}", result.page.status, result.page.content_type);
+    /// for (url, error) in &result.errors {
+    ///     println!("skipped {}: {}", url, error);
+    /// }
+    /// ```
+    ///
+    /// Relative references are joined to `final_url`, so after a redirect from
+    /// `http` to `https` the icons are loaded over `https` as well.
+    pub fn from_website_detailed<P>(
+        base_url: P,
+        options: &ExtractOptions,
+    ) -> Result<ExtractionResult, IconError>
+    where
+        P: AsRef<str>,
+    {
+        let base_urls = page_urls(base_url.as_ref(), options)?;
+        let session = Session::new(options)?;
+        ImageLink::extract_with(&session, base_urls)
+    }
+
+    /// Extract the icons of a website and report what happened to every reference,
+    /// to find out why an icon is missing from [`ImageLink::from_website_with_options`]
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{AuditOutcome, ExtractOptions, ImageLink};
+    ///
+    /// let audit = ImageLink::audit("https://example.com", &ExtractOptions::default()).unwrap();
+    /// for entry in &audit.entries {
+    ///     match &entry.outcome {
+    ///         AuditOutcome::Kept(icon) => println!("{} {}x{}", icon.url, icon.width, icon.height),
+    ///         outcome => println!("{:?} {:?}: {:?}", entry.href, entry.url, outcome),
+    ///     }
+    /// }
+    /// ```
+    pub fn audit<P>(base_url: P, options: &ExtractOptions) -> Result<ExtractionAudit, IconError>
+    where
+        P: AsRef<str>,
+    {
+        let base_urls = page_urls(base_url.as_ref(), options)?;
+        let session = Session::new(options)?;
+        ImageLink::audit_first(&session, base_urls)
+    }
+
+    /// Extract the icons of many websites with one shared http client.
+    /// Up to [`ExtractOptions::max_concurrency`] sites are processed at the same time,
+    /// the icons of each site are probed one after another.
+    /// Icon urls that were measured for an earlier site are not fetched again,
+    /// see [`ImageLink::from_cache`].
+    /// The results are in the order of `urls`. See [`ExtractOptions::user_agents`]
+    /// to rotate the user agent between hosts.
+    /// # Arguments
+    /// * `urls` - Urls of the websites to check
+    /// * `options` - Options used for every website
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink};
+    ///
+    /// let options = ExtractOptions {
+    ///     user_agents: vec![String::from("agent-a"), String::from("agent-b")],
+    ///     ..Default::default()
+    /// };
+    /// let urls = ["https://example.com", "https://example.org"];
+    /// for result in ImageLink::from_websites(&urls, &options).unwrap() {
+    ///     println!("{:?}", result.map(|result| result.icons));
+    /// }
+    /// ```
+    pub fn from_websites<P>(
+        urls: &[P],
+        options: &ExtractOptions,
+    ) -> Result<Vec<Result<ExtractionResult, IconError>>, IconError>
+    where
+        P: AsRef<str>,
+    {
+        let client = match options.fetcher {
+            Some(_) => None,
+            None => Some(options.build_client()?),
+        };
+        let icon_client = options.build_icon_client()?;
+        let cache: Mutex<HashMap<Url, ImageLink>> = Mutex::new(HashMap::new());
+        let mut assigned: HashMap<String, usize> = HashMap::new();
+        let jobs: Vec<_> = urls
             .iter()
-            .filter_map(|unfiltered_url| base_url.join(&unfiltered_url).ok())
-            .filter_map(|image_url| ImageLink::new(image_url, user_agent.as_ref(), tcp_timeout).ok())
-            .collect())
+            .map(|url| {
+                let url = page_urls(url.as_ref(), options);
+                let user_agent = match &url {
+                    Ok(urls) if !options.user_agents.is_empty() => {
+                        let next = assigned.len();
+                        let host = urls[0].host_str().unwrap_or("").to_string();
+                        let index = *assigned.entry(host).or_insert(next);
+                        Some(options.user_agents[index % options.user_agents.len()].clone())
+                    }
+                    _ => None,
+                };
+                (url, user_agent)
+            })
+            .collect();
+        let limiter = options.rate_limiter();
+        let host_limiter = options.host_limiter();
+        Ok(pool::run_bounded(jobs, options.max_concurrency, |(url, user_agent)| {
+            let fetcher: Arc<dyn IconFetcher> = match (&options.fetcher, &client) {
+                (Some(fetcher), _) => fetcher.clone(),
+                (None, Some(client)) => Arc::new(ReqwestFetcher {
+                    client: client.clone(),
+                    user_agent: user_agent.clone(),
+                    identity_ranges: options.identity_encoding_for_probes,
+                }),
+                (None, None) => unreachable!("client is built if there is no fetcher"),
+            };
+            let fetcher = host_limited(rate_limited(fetcher, limiter.clone()), host_limiter.clone());
+            let mut session = Session::with_fetcher(fetcher, options, 1);
+            session.use_icon_client(icon_client.clone(), user_agent, limiter.clone(), host_limiter.clone());
+            session.cache = Some(&cache);
+            ImageLink::extract_with(&session, url?)
+        }))
+    }
+
+    /// Extract the icons of one website with the client of the session
+    fn extract_with(session: &Session, base_urls: Vec<Url>) -> Result<ExtractionResult, IconError> {
+        Ok(ImageLink::audit_first(session, base_urls)?.into())
+    }
+
+    /// Audit the first of the urls that can be extracted, the error of the last one otherwise
+    fn audit_first(session: &Session, base_urls: Vec<Url>) -> Result<ExtractionAudit, IconError> {
+        let mut last_error = None;
+        for base_url in base_urls {
+            let description = base_url.to_string();
+            match ImageLink::audit_with(session, base_url) {
+                Ok(audit) => return Ok(audit),
+                Err(err) => {
+                    trace!("{}, extraction failed: {}", description, err);
+                    last_error = Some(err);
+                }
+            }
+        }
+        Err(last_error.unwrap_or(IconError::Url(url::ParseError::EmptyHost)))
+    }
+
+    /// Audit the extraction of one website with the client of the session
+    fn audit_with(session: &Session, base_url: Url) -> Result<ExtractionAudit, IconError> {
+        let options = session.options;
+        let start = Instant::now();
+        let response = match session.fetch_start_page(&base_url) {
+            Err(err) if err.is_connect() && options.www_fallback => {
+                let alternative = toggle_www(&base_url).ok_or(err)?;
+                trace!("{}, connecting failed, trying {}", base_url, alternative);
+                session.fetch_start_page(&alternative)?
+            }
+            result => result?,
+        };
+        if options.require_success && !(200..300).contains(&response.status) {
+            return Err(IconError::PageStatus(response.status));
+        }
+        let final_url = response.url.clone();
+        let page_response = PageResponse {
+            status: response.status,
+            content_type: header_string(&response.headers, CONTENT_TYPE),
+            content_length: header_string(&response.headers, CONTENT_LENGTH).and_then(|length| length.parse().ok()),
+            server: header_string(&response.headers, SERVER),
+        };
+
+        let mut page = analyze_location(&response, options);
+        // relative references are joined to the url the page was actually served from
+        let mut page_url = final_url.clone();
+        if page.refs.is_empty() && options.follow_canonical {
+            if let Some((canonical_page, canonical_url)) = analyze_canonical_page(session, &page, &final_url) {
+                if canonical_page.refs.is_empty() {
+                    page.html_bytes += canonical_page.html_bytes;
+                } else {
+                    page = AnalyzedPage {
+                        refs: canonical_page.refs,
+                        html_bytes: page.html_bytes + canonical_page.html_bytes,
+                        metadata: page.metadata,
+                        canonical: page.canonical,
+                    };
+                    page_url = canonical_url;
+                }
+            }
+        }
+        if page.refs.is_empty() && options.sitemap_fallback {
+            if let Some((sitemap_page, sitemap_page_url)) = analyze_sitemap_page(session, &final_url) {
+                page = AnalyzedPage {
+                    refs: sitemap_page.refs,
+                    html_bytes: page.html_bytes + sitemap_page.html_bytes,
+                    metadata: page.metadata,
+                    canonical: page.canonical,
+                };
+                page_url = sitemap_page_url;
+            }
+        }
+        let declared = page.refs.iter().map(|icon_ref| {
+            let candidate = resolve_href(&page_url, &icon_ref.href).map(|url| Candidate {
+                url: options.strip_query(url),
+                source: icon_ref.source,
+                declared_size: icon_ref.declared_size,
+                declared_sizes: icon_ref.declared_sizes.clone(),
+                declared_type: icon_ref.declared_type,
+                declared_any: icon_ref.declared_any,
+            });
+            (Some(icon_ref.href.clone()), icon_ref.source, candidate)
+        });
+        let guessed = options.favicon_location.urls(&final_url).into_iter().map(|url| {
+            let candidate = Candidate {
+                url,
+                source: IconSource::DefaultFavicon,
+                declared_size: None,
+                declared_sizes: Vec::new(),
+                declared_type: None,
+                declared_any: false,
+            };
+            (None, IconSource::DefaultFavicon, Ok(candidate))
+        });
+        let mut entries: Vec<(Option<String>, IconSource, Option<Url>)> = Vec::new();
+        let mut outcomes: Vec<Option<AuditOutcome>> = Vec::new();
+        let mut candidates: Vec<(usize, Candidate)> = Vec::new();
+        for (href, source, candidate) in declared.chain(guessed) {
+            let url = candidate.as_ref().ok().map(|candidate| candidate.url.clone());
+            let outcome = match candidate {
+                _ if !options.wants_source(source) => Some(AuditOutcome::Excluded),
+                _ if href.as_deref().is_some_and(is_template_placeholder) => Some(AuditOutcome::Filtered(
+                    IconError::TemplatePlaceholder(href.clone().unwrap_or_default()),
+                )),
+                Err(error) => Some(AuditOutcome::Filtered(error)),
+                Ok(candidate) => {
+                    if let Err(error) = check_scheme(&candidate.url) {
+                        Some(AuditOutcome::Filtered(error))
+                    } else if let Err(error) = options.check_host(&page_url, &candidate.url) {
+                        Some(AuditOutcome::Filtered(error))
+                    } else if candidates.iter().any(|(_, other)| other.url == candidate.url) {
+                        Some(AuditOutcome::DuplicateUrl)
+                    } else if options.max_candidates.is_some_and(|max| candidates.len() >= max) {
+                        Some(AuditOutcome::OverLimit)
+                    } else {
+                        candidates.push((entries.len(), candidate));
+                        None
+                    }
+                }
+            };
+            entries.push((href, source, url));
+            outcomes.push(outcome);
+        }
+        let upgrade = options.upgrade_insecure && final_url.scheme() == "https";
+        let probed = pool::run_bounded(candidates, session.workers, |(index, candidate)| {
+            if let Some(icon) = session.cached(&candidate) {
+                return (index, Ok(icon));
+            }
+            let url = candidate.url.clone();
+            let icon = if upgrade {
+                ImageLink::probe_upgraded(session, candidate)
+            } else {
+                ImageLink::probe(session, candidate)
+            };
+            if let (Ok(icon), Some(cache)) = (&icon, session.cache) {
+                // icons taken from their declaration are only valid for that page
+                if let (true, Ok(mut cache)) = (icon.measured, cache.lock()) {
+                    cache.insert(url, icon.clone());
+                }
+            }
+            (index, icon)
+        });
+        for (index, icon) in probed {
+            outcomes[index] = Some(match icon {
+                Ok(icon) => AuditOutcome::Kept(icon),
+                Err(error) => AuditOutcome::Failed(error),
+            });
+        }
+        if options.dedup_by_content {
+            dedup_by_content(&mut outcomes);
+        }
+        let entries: Vec<AuditEntry> = entries
+            .into_iter()
+            .zip(outcomes)
+            .filter_map(|((href, source, url), outcome)| {
+                Some(AuditEntry {
+                    href,
+                    source,
+                    url,
+                    outcome: outcome?,
+                })
+            })
+            .collect();
+        if let Some(SkipHandler(handler)) = &options.on_skip {
+            for entry in entries.iter().filter(|entry| !matches!(entry.outcome, AuditOutcome::Kept(_))) {
+                handler(entry);
+            }
+        }
+        Ok(ExtractionAudit {
+            requested_url: base_url,
+            final_url,
+            base_url: page_url,
+            page: page_response,
+            entries,
+            html_bytes: page.html_bytes,
+            icon_bytes_total: session.icon_bytes.load(Ordering::Relaxed),
+            metadata: page.metadata,
+            elapsed: start.elapsed(),
+        })
     }
 }
 
-/// Search html content for links to icons and return them
-fn analyze_content(content: &str) -> Result<Vec<String>, Box<dyn Error>> {
-    let mut reader = Reader::from_str(content);
-    reader.trim_text(true);
-    reader.check_end_names(false);
-    let mut buf = Vec::new();
-    let mut list: Vec<String> = Vec::new();
+/// Extract all icons of a website, same as [`ImageLink::from_website`]
+/// # Arguments
+/// * `url` - An url to check
+/// * `user_agent` - User agent header string for http requests
+/// * `tcp_timeout` - Http timeout in seconds
+pub fn extract_icons<P, Q>(url: P, user_agent: Q, tcp_timeout: u64) -> Result<Vec<ImageLink>, IconError>
+where
+    P: AsRef<str>,
+    Q: AsRef<str>,
+{
+    ImageLink::from_website(url, user_agent, tcp_timeout)
+}
 
-    loop {
-        match reader.read_event_into(&mut buf) {
-            Ok(Event::Empty(ref e)) => {
-                list.extend(check_start_elem(&reader, e));
+/// Sort icons by pixel area, largest first.
+/// The sort is stable, icons of equal size keep their order.
+/// # Example
+/// ```rust
+/// use website_icon_extract::{sort_by_size, ExtractOptions, ImageLink};
+///
+/// let options = ExtractOptions::default();
+/// let mut icons: Vec<ImageLink> = ["0 0 16 16", "0 0 64 64", "0 0 32 32"]
+///     .iter()
+///     .map(|view_box| format!("data:image/svg+xml,<svg viewBox='{}'/>", view_box))
+///     .map(|url| ImageLink::new_with_options(url, &options).unwrap())
+///     .collect();
+/// sort_by_size(&mut icons);
+/// let widths: Vec<usize> = icons.iter().map(|icon| icon.width).collect();
+/// assert_eq!(widths, vec![64, 32, 16]);
+/// ```
+pub fn sort_by_size(icons: &mut [ImageLink]) {
+    icons.sort_by_key(|icon| std::cmp::Reverse(icon.width * icon.height));
+}
+
+/// Pick the icon closest to a target size, for example the pixel size an icon is rendered at.
+/// Icons at least as large as the target are preferred to avoid upscaling,
+/// otherwise the largest one is used. Ties go to the smaller file.
+/// Scalable icons fit every target exactly.
+/// # Example
+/// ```rust
+/// use website_icon_extract::{closest_to, ExtractOptions, ImageLink};
+///
+/// let png = |size: u8| format!(
+///     "data:image/png,%89PNG%0D%0A%1A%0A%00%00%00%0DIHDR%00%00%00%{:02X}%00%00%00%{:02X}",
+///     size, size
+/// );
+/// let options = ExtractOptions::default();
+/// let icons: Vec<ImageLink> = [16, 32, 96, 128]
+///     .iter()
+///     .map(|size| ImageLink::new_with_options(png(*size), &options).unwrap())
+///     .collect();
+/// assert_eq!(closest_to(&icons, 64).unwrap().width, 96);
+/// assert_eq!(closest_to(&icons, 32).unwrap().width, 32);
+/// assert_eq!(closest_to(&icons, 256).unwrap().width, 128);
+/// ```
+pub fn closest_to(icons: &[ImageLink], target: usize) -> Option<&ImageLink> {
+    let size = |icon: &ImageLink| {
+        if icon.scalable {
+            target
+        } else {
+            icon.width.max(icon.height)
+        }
+    };
+    let file_size = |icon: &ImageLink| icon.file_size.unwrap_or(u64::MAX);
+    icons
+        .iter()
+        .filter(|icon| size(icon) >= target)
+        .min_by_key(|icon| (size(icon), file_size(icon)))
+        .or_else(|| {
+            icons
+                .iter()
+                .max_by_key(|icon| (size(icon), std::cmp::Reverse(file_size(icon))))
+        })
+}
+
+/// Pixel sizes `<link>` tags are commonly generated for: browser tabs,
+/// the apple touch icon and the icons of a web app manifest
+pub const DEFAULT_LINK_SIZES: &[usize] = &[16, 32, 48, 180, 192, 512];
+
+/// Icon picked for one target size by [`link_icons`]
+#[derive(Clone, Debug)]
+pub struct LinkIcon {
+    /// Target width and height in pixels
+    pub size: usize,
+    /// Square icon to generate the size from
+    pub icon: ImageLink,
+    /// The icon is smaller than the target and would have to be scaled up
+    pub upscaled: bool,
+}
+
+/// Pick one square icon for each target size, for generating favicon `<link>` tags.
+/// The icon is chosen like [`closest_to`] among the square and scalable icons only,
+/// targets stay without an icon if there are none of them.
+/// # Arguments
+/// * `icons` - Measured or declared icons, for example from [`ImageLink::from_website_with_options`]
+/// * `sizes` - Target sizes in pixels, like [`DEFAULT_LINK_SIZES`]
+/// # Example
+/// ```rust
+/// use website_icon_extract::{link_icons, ExtractOptions, ImageLink, DEFAULT_LINK_SIZES};
+///
+/// let png = |width: u8, height: u8| format!(
+///     "data:image/png,%89PNG%0D%0A%1A%0A%00%00%00%0DIHDR%00%00%00%{:02X}%00%00%00%{:02X}",
+///     width, height
+/// );
+/// let options = ExtractOptions::default();
+/// let icons: Vec<ImageLink> = [(16, 16), (32, 32), (180, 180), (200, 100)]
+///     .iter()
+///     .map(|(width, height)| ImageLink::new_with_options(png(*width, *height), &options).unwrap())
+///     .collect();
+/// let links = link_icons(&icons, DEFAULT_LINK_SIZES);
+/// let picked: Vec<(usize, usize, bool)> = links.iter().map(|link| (link.size, link.icon.width, link.upscaled)).collect();
+/// assert_eq!(
+///     picked,
+///     vec![(16, 16, false), (32, 32, false), (48, 180, false), (180, 180, false), (192, 180, true), (512, 180, true)]
+/// );
+/// ```
+pub fn link_icons(icons: &[ImageLink], sizes: &[usize]) -> Vec<LinkIcon> {
+    let squares = icons.squares();
+    sizes
+        .iter()
+        .filter_map(|size| {
+            let icon = closest_to(&squares, *size)?;
+            Some(LinkIcon {
+                size: *size,
+                upscaled: !icon.scalable && icon.width < *size,
+                icon: icon.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Chainable selection helpers for lists of icons
+/// # Example
+/// ```rust
+/// use website_icon_extract::{ExtractOptions, IconListExt, ImageLink};
+///
+/// let png = |width: u8, height: u8| format!(
+///     "data:image/png,%89PNG%0D%0A%1A%0A%00%00%00%0DIHDR%00%00%00%{:02X}%00%00%00%{:02X}",
+///     width, height
+/// );
+/// let options = ExtractOptions::default();
+/// let icons: Vec<ImageLink> = [(16, 16), (64, 32), (48, 48), (16, 16)]
+///     .iter()
+///     .map(|(width, height)| ImageLink::new_with_options(png(*width, *height), &options).unwrap())
+///     .collect();
+/// let squares = icons.deduped().squares().sorted_by_area();
+/// let widths: Vec<usize> = squares.iter().map(|icon| icon.width).collect();
+/// assert_eq!(widths, vec![48, 16]);
+/// assert_eq!(squares.best().unwrap().width, 48);
+/// ```
+pub trait IconListExt {
+    /// Drop icons with the same url as an earlier one
+    fn deduped(self) -> Vec<ImageLink>;
+    /// Keep square icons with a size and scalable icons
+    fn squares(self) -> Vec<ImageLink>;
+    /// Sort by pixel area, largest first, see [`sort_by_size`]
+    fn sorted_by_area(self) -> Vec<ImageLink>;
+    /// A scalable icon if there is one, otherwise the largest square icon
+    /// and otherwise the largest icon
+    fn best(self) -> Option<ImageLink>;
+}
+
+impl IconListExt for Vec<ImageLink> {
+    fn deduped(self) -> Vec<ImageLink> {
+        let mut kept: Vec<ImageLink> = Vec::new();
+        for icon in self {
+            if !kept.iter().any(|other| other.url == icon.url) {
+                kept.push(icon);
             }
-            Ok(Event::Start(ref e)) => {
-                list.extend(check_start_elem(&reader, e));
+        }
+        kept
+    }
+
+    fn squares(self) -> Vec<ImageLink> {
+        self.as_slice().squares()
+    }
+
+    fn sorted_by_area(mut self) -> Vec<ImageLink> {
+        sort_by_size(&mut self);
+        self
+    }
+
+    fn best(self) -> Option<ImageLink> {
+        let mut icons = self.sorted_by_area();
+        let index = icons
+            .iter()
+            .position(|icon| icon.scalable)
+            .or_else(|| icons.iter().position(|icon| icon.width > 0 && icon.width == icon.height))
+            .or_else(|| (!icons.is_empty()).then_some(0))?;
+        Some(icons.swap_remove(index))
+    }
+}
+
+impl IconListExt for &[ImageLink] {
+    fn deduped(self) -> Vec<ImageLink> {
+        self.to_vec().deduped()
+    }
+
+    fn squares(self) -> Vec<ImageLink> {
+        self.iter()
+            .filter(|icon| icon.scalable || (icon.width > 0 && icon.width == icon.height))
+            .cloned()
+            .collect()
+    }
+
+    fn sorted_by_area(self) -> Vec<ImageLink> {
+        self.to_vec().sorted_by_area()
+    }
+
+    fn best(self) -> Option<ImageLink> {
+        self.to_vec().best()
+    }
+}
+
+/// Only `http`, `https` and `data` urls can be probed
+fn check_scheme(url: &Url) -> Result<(), IconError> {
+    if matches!(url.scheme(), "http" | "https" | "data") {
+        Ok(())
+    } else {
+        Err(IconError::UnsupportedScheme(url.scheme().to_string()))
+    }
+}
+
+/// Urls to try for a page given by the caller, see [`ExtractOptions::try_all_schemes`]
+fn page_urls(input: &str, options: &ExtractOptions) -> Result<Vec<Url>, url::ParseError> {
+    let input = input.trim();
+    if options.try_all_schemes && !input.contains("://") {
+        Ok(vec![
+            Url::parse(&format!("https://{}", input))?,
+            Url::parse(&format!("http://{}", input))?,
+        ])
+    } else {
+        Ok(vec![Url::parse(input)?])
+    }
+}
+
+/// Same url with the `www.` prefix of the host added or removed,
+/// `None` for ip addresses and hosts without a dot
+fn toggle_www(url: &Url) -> Option<Url> {
+    let host = match url.host()? {
+        url::Host::Domain(host) => host,
+        _ => return None,
+    };
+    let toggled = match host.strip_prefix("www.") {
+        Some(bare) => bare.to_string(),
+        None => format!("www.{}", host),
+    };
+    if !toggled.contains('.') {
+        return None;
+    }
+    let mut alternative = url.clone();
+    alternative.set_host(Some(&toggled)).ok()?;
+    Some(alternative)
+}
+
+/// Join an icon reference to the url of the page it was found in
+fn resolve_href(base: &Url, href: &str) -> Result<Url, IconError> {
+    let href = href.trim();
+    if href.is_empty() || href.starts_with('#') {
+        return Err(IconError::EmptyHref);
+    }
+    Ok(base.join(href)?)
+}
+
+/// Whether an href still contains the syntax of a server side template.
+/// A name between two percent signs like `%ASSET_URL%` has to be uppercase and at least
+/// three characters long, so percent-encoded text like `%C3%A9cole%20` is not taken for one.
+fn is_template_placeholder(href: &str) -> bool {
+    if ["{{", "}}", "${", "<%", "%>"].iter().any(|marker| href.contains(marker)) {
+        return true;
+    }
+    let mut parts = href.split('%').skip(1).peekable();
+    while let Some(part) = parts.next() {
+        let is_name = part.len() > 2
+            && part.starts_with(|c: char| c.is_ascii_uppercase() || c == '_')
+            && part.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+        if is_name && parts.peek().is_some() {
+            return true;
+        }
+    }
+    false
+}
+
+/// Collapse icons that have the same content hash and size.
+/// The first position is kept, the url is the shortest of the group.
+fn dedup_by_content(outcomes: &mut [Option<AuditOutcome>]) {
+    let mut kept: Vec<usize> = Vec::new();
+    for index in 0..outcomes.len() {
+        let icon = match &outcomes[index] {
+            Some(AuditOutcome::Kept(icon)) => icon,
+            _ => continue,
+        };
+        let duplicate = kept.iter().copied().find(|other| match &outcomes[*other] {
+            Some(AuditOutcome::Kept(other)) => {
+                icon.content_hash.is_some()
+                    && other.content_hash == icon.content_hash
+                    && other.width == icon.width
+                    && other.height == icon.height
             }
-            Ok(Event::End(_)) => {}
-            Ok(Event::Text(_)) => {}
-            Ok(Event::Eof) => break,
-            Err(e) => {
-                println!("Error at position {}: {:?}", reader.buffer_position(), e);
-                //break;
+            _ => false,
+        });
+        match duplicate {
+            Some(other) => {
+                let outcome = outcomes[index].replace(AuditOutcome::DuplicateContent(other));
+                if let (Some(AuditOutcome::Kept(icon)), Some(AuditOutcome::Kept(other_icon))) =
+                    (outcome, &mut outcomes[other])
+                {
+                    if icon.url.as_str().len() < other_icon.url.as_str().len() {
+                        *other_icon = icon;
+                    }
+                }
             }
-            _ => (), // There are several other `Event`s we do not consider here
+            None => kept.push(index),
         }
-        buf.clear();
     }
-    Ok(list)
+}
+
+/// Number of bytes downloaded to measure an image. Most formats need less, but an ico
+/// directory takes 6 bytes plus 16 per image, so this reads the directory of up to 16 images.
+/// Probing costs a little more for other formats, instead of a second request for ico files.
+const PROBE_BYTES: u64 = 6 + 16 * 16;
+
+/// Growing range lengths for [`ExtractOptions::max_probe_bytes`]
+const PROGRESSIVE_PROBE_BYTES: [u64; 3] = [32, 512, 4096];
+
+/// Information gathered by downloading the start of an image
+struct Probe {
+    size: ImageSize,
+    image_type: Option<ImageType>,
+    scalable: bool,
+    /// Hash of the downloaded bytes, if enough of the file was read
+    content_hash: Option<u64>,
+    /// Raw Content-Type header sent by the server
+    content_type: Option<String>,
+    /// Duration of the request
+    elapsed: Option<Duration>,
+    /// Size of the complete file
+    file_size: Option<u64>,
+    /// Sizes listed in the directory of an ico file
+    contained_sizes: Vec<(usize, usize)>,
+    /// The server sent only the requested range
+    partial: bool,
 }
 
 /// Download part of the file and try to load as image.
-/// If possible return pixel dimensions (x,y)
-fn get_pixel_size<U: IntoUrl, P: AsRef<str>>(
-    url: U,
-    user_agent: P,
-    tcp_timeout: u64,
-) -> Result<(ImageSize, ImageType), Box<dyn Error>> {
-    let url = url.into_url()?;
-    let response = Client::new()
-        .get(url.clone())
-        .timeout(Duration::new(tcp_timeout, 0))
-        .header(RANGE, "bytes=0-99")
-        .header(USER_AGENT, user_agent.as_ref())
-        .send()?;
-    let data: Vec<u8> = response.bytes()?.to_vec();
-    let pixel_size = blob_size(&data)?;
+/// If possible return pixel dimensions (x,y) and a hash of the downloaded bytes
+fn get_pixel_size(session: &Session, url: Url) -> Result<Probe, IconError> {
+    let start = Instant::now();
+    let mut lengths = probe_lengths(session.options).into_iter().peekable();
+    let (pixel_size, data, content_type, file_size, partial, length) = loop {
+        let length = lengths.next().unwrap_or(PROBE_BYTES);
+        let more = lengths.peek().is_some();
+        let response = session.icon_fetcher.fetch_range(&url, length)?;
+        let content_type = header_string(&response.headers, CONTENT_TYPE);
+        let file_size = total_size(&response);
+        let partial = response.status == StatusCode::PARTIAL_CONTENT.as_u16();
+        let data = response.body;
+        session.icon_bytes.fetch_add(data.len(), Ordering::Relaxed);
+        // the body is already read, but junk this small is not worth measuring
+        if let Some(size) = file_size.filter(|size| *size < session.options.min_icon_bytes) {
+            return Err(IconError::TooSmall(size));
+        }
+        if data.is_empty() {
+            return Err(IconError::EmptyBody);
+        }
+        // the type is sniffed from the bytes, misconfigured servers send icons as
+        // application/octet-stream or with the type of another format,
+        // so the Content-Type only counts if the bytes are not recognized
+        let detected = match image_type(&data) {
+            Ok(sniffed) => Some((sniffed, format!("{:?}", sniffed))),
+            Err(_) => content_type
+                .as_deref()
+                .and_then(|mime| Some((image_type_from_mime(mime)?, mime.to_string()))),
+        };
+        if let Some((detected, description)) = detected {
+            session.options.check_type(Some(detected), &description)?;
+        }
+        let read = data.len() as u64;
+        match blob_size(&data) {
+            Ok(_) if more && is_truncated(read, length, file_size) && ico_directory_incomplete(&data) => {
+                trace!("{}, ico directory not in the first {} bytes, retrying with more", url, read);
+            }
+            Ok(pixel_size) => break (pixel_size, data, content_type, file_size, partial, length),
+            Err(err) if more && is_short_read(&err, read, length, file_size) => {
+                trace!("{}, size not in the first {} bytes, retrying with more", url, read);
+            }
+            Err(err) => return Err(err.into()),
+        }
+    };
+    let elapsed = start.elapsed();
     let image_type = image_type(&data)?;
     trace!(
-        "{}, downloaded bytes: {}, pixels: {}x{}, type: {:?}",
+        "{}, downloaded bytes: {} in {:?}, pixels: {}x{}, type: {:?}, content type: {:?}",
         url,
         data.len(),
+        elapsed,
         pixel_size.width,
         pixel_size.height,
-        image_type
+        image_type,
+        content_type
     );
-    Ok((pixel_size, image_type))
+    // a short progressive probe of a png holds little more than its size,
+    // so files that go on after it are not hashed
+    let read = data.len() as u64;
+    let content_hash = (read >= PROBE_BYTES || !is_truncated(read, length, file_size)).then(|| {
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        hasher.finish()
+    });
+    Ok(Probe {
+        size: pixel_size,
+        image_type: Some(image_type),
+        scalable: false,
+        content_hash,
+        content_type,
+        elapsed: Some(elapsed),
+        file_size,
+        contained_sizes: ico_directory_sizes(Some(image_type), &data),
+        partial,
+    })
 }
 
-/// Download the file and analyze the content
-/// Try to extract links to images.
-/// # Returns
-/// List of image urls
-fn analyze_location(response: Response) -> Result<Vec<String>, Box<dyn Error>> {
-    let content_type = response.headers().get(CONTENT_TYPE);
-    if let Some(content_type) = content_type {
-        if content_type.to_str().unwrap_or("").starts_with("text/html") {
-            let content = response.text()?;
-            let list = analyze_content(&content)?;
-            return Ok(list);
-        }
-    }
-    Ok(Vec::new())
-}
-
-fn attr_to_hash(
-    reader: &quick_xml::Reader<&[u8]>,
-    e: quick_xml::events::attributes::Attributes,
-) -> HashMap<String, String> {
-    let attrs_hashed: HashMap<String, String> = e
-        .filter(|x| x.is_ok())
-        .map(|x| x.unwrap())
-        .map(|x| {
-            (
-                reader.decoder().decode(x.key.local_name().as_ref()).map(|b| b.to_string().to_lowercase()),
-                reader.decoder().decode(&x.value).map(|c| c.to_string()),
-            )
-        })
-        .filter(|i| i.0.is_ok() && i.1.is_ok())
-        .map(|j| (j.0.unwrap(), j.1.unwrap()))
-        .collect();
-    attrs_hashed
-}
-
-fn extract(
-    attrs_hashed: &HashMap<String, String>,
-    names: &Vec<String>,
-    key_name: &str,
-    content: &str,
-) -> Vec<String> {
-    let mut list: Vec<String> = vec![];
-    let name: Option<&String> = attrs_hashed.get(key_name);
-    let content = attrs_hashed.get(content);
-    if let Some(name) = name {
-        if let Some(content) = content {
-            let name: String = name.to_lowercase();
-            let content = content.to_lowercase();
-            if names.contains(&name) {
-                list.push(content.to_string());
+/// Range lengths to probe an image with, each one only if the one before was too short
+fn probe_lengths(options: &ExtractOptions) -> Vec<u64> {
+    match options.max_probe_bytes {
+        Some(max) => {
+            let mut lengths: Vec<u64> = PROGRESSIVE_PROBE_BYTES.iter().map(|length| (*length).min(max)).collect();
+            lengths.dedup();
+            lengths
+        }
+        None => (0..=options.min_probe_retries_on_short_read as u32)
+            .map(|retry| PROBE_BYTES.saturating_mul(4u64.saturating_pow(retry)))
+            .collect(),
+    }
+}
+
+/// Whether the file goes on after the downloaded bytes
+fn is_truncated(read: u64, requested: u64, file_size: Option<u64>) -> bool {
+    match file_size {
+        Some(size) => read < size,
+        None => read >= requested,
+    }
+}
+
+/// Whether measuring failed because the downloaded bytes end before the size,
+/// while the file goes on after them
+fn is_short_read(err: &ImageError, read: u64, requested: u64, file_size: Option<u64>) -> bool {
+    // an unknown format can only be caused by reading too little if not even the signature arrived
+    is_truncated(read, requested, file_size) && (read < requested || !matches!(err, ImageError::NotSupported))
+}
+
+/// Whether the data is the start of an ico file whose directory goes on after it.
+/// The size of such a file is the largest of the entries read so far.
+fn ico_directory_incomplete(data: &[u8]) -> bool {
+    if !matches!(image_type(data), Ok(ImageType::Ico)) || data.len() < 6 {
+        return false;
+    }
+    let count = u16::from_le_bytes([data[4], data[5]]) as usize;
+    data.len() < 6 + 16 * count
+}
+
+/// Measure an image embedded in a `data:` url without any request
+fn decode_data_image(url: &Url) -> Result<Probe, IconError> {
+    let data_url = data_url::decode(url).ok_or(IconError::InvalidDataUrl)?;
+    if data_url.data.is_empty() {
+        return Err(IconError::EmptyBody);
+    }
+    let (size, image_type, scalable) = if data_url.media_type == "image/svg+xml" {
+        let size = svg_size(&data_url.data).ok_or(IconError::Image(ImageError::NotSupported))?;
+        (size, None, true)
+    } else {
+        (blob_size(&data_url.data)?, Some(image_type(&data_url.data)?), false)
+    };
+    let mut hasher = DefaultHasher::new();
+    data_url.data.hash(&mut hasher);
+    Ok(Probe {
+        size,
+        image_type,
+        scalable,
+        content_hash: Some(hasher.finish()),
+        elapsed: None,
+        file_size: Some(data_url.data.len() as u64),
+        contained_sizes: ico_directory_sizes(image_type, &data_url.data),
+        partial: false,
+        content_type: Some(data_url.media_type),
+    })
+}
+
+/// Sizes of the complete entries of an ico directory, empty for other formats
+fn ico_directory_sizes(image_type: Option<ImageType>, data: &[u8]) -> Vec<(usize, usize)> {
+    if image_type != Some(ImageType::Ico) || data.len() < 6 {
+        return Vec::new();
+    }
+    let count = u16::from_le_bytes([data[4], data[5]]) as usize;
+    // a width or height of 0 stands for 256
+    let pixels = |value: u8| if value == 0 { 256 } else { value as usize };
+    data[6..]
+        .chunks_exact(16)
+        .take(count)
+        .map(|entry| (pixels(entry[0]), pixels(entry[1])))
+        .collect()
+}
+
+/// Intrinsic size of a svg document from the `width`/`height` attributes
+/// or the `viewBox` of the root element. `None` if the data is not svg.
+fn svg_size(data: &[u8]) -> Option<ImageSize> {
+    let mut reader = Reader::from_reader(data);
+    reader.check_end_names(false);
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                if e.name().local_name().as_ref() != b"svg" {
+                    return None;
+                }
+                let attrs = attr_to_hash(&reader, e.attributes());
+                let view_box: Vec<f64> = attrs
+                    .get("viewbox")
+                    .map(|v| {
+                        v.split(|c: char| c == ',' || c.is_whitespace())
+                            .filter_map(|n| n.parse().ok())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let length = |name: &str, index: usize| {
+                    attrs
+                        .get(name)
+                        .and_then(|v| v.trim().trim_end_matches("px").parse::<f64>().ok())
+                        .or_else(|| view_box.get(index).copied().filter(|_| view_box.len() == 4))
+                        .map(|v| v.max(0.0).round() as usize)
+                        .unwrap_or(0)
+                };
+                return Some(ImageSize {
+                    width: length("width", 2),
+                    height: length("height", 3),
+                });
             }
+            Ok(Event::Eof) | Err(_) => return None,
+            _ => {}
         }
+        buf.clear();
     }
-    list
-}
-
-/// Check a single html element if it does contain a link to a describing image
-fn check_start_elem(
-    reader: &quick_xml::Reader<&[u8]>,
-    e: &quick_xml::events::BytesStart<'_>,
-) -> Vec<String> {
-    let meta_name_attrs: Vec<String> = vec![
-        String::from("msapplication-TileImage"),
-        String::from("msapplication-square70x70logo"),
-        String::from("msapplication-square150x150logo"),
-        String::from("msapplication-square310x310logo"),
-        String::from("msapplication-wide310x150logo"),
-    ];
-    let meta_property_attrs: Vec<String> = vec![String::from("og:image")];
-    let link_rel_attrs: Vec<String> = vec![
-        String::from("apple-touch-icon"),
-        String::from("shortcut icon"),
-        String::from("icon"),
-    ];
-    let mut list: Vec<String> = Vec::new();
-
-    match e.name().local_name().as_ref() {
-        b"meta" => {
-            let attrs_hashed = attr_to_hash(&reader, e.attributes());
-            let l = extract(&attrs_hashed, &meta_name_attrs, "name", "content");
-            list.extend(l);
-            let l = extract(&attrs_hashed, &meta_property_attrs, "property", "content");
-            list.extend(l);
-        }
-        b"link" => {
-            let attrs_hashed = attr_to_hash(&reader, e.attributes());
-            let l = extract(&attrs_hashed, &link_rel_attrs, "rel", "href");
-            list.extend(l);
-        }
-        _ => {}
+}
+
+/// Size of the complete file, from the total of Content-Range for partial responses
+/// or from Content-Length for complete ones
+fn total_size(response: &FetchedResponse) -> Option<u64> {
+    let header = if response.status == StatusCode::PARTIAL_CONTENT.as_u16() {
+        header_string(&response.headers, CONTENT_RANGE)?.rsplit('/').next()?.to_string()
+    } else {
+        header_string(&response.headers, CONTENT_LENGTH)?
     };
+    header.trim().parse().ok()
+}
+
+/// Value of a response header, if present and valid text
+fn header_string(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+/// What was found in a downloaded page
+struct AnalyzedPage {
+    /// References to icons
+    refs: Vec<IconRef>,
+    /// Bytes read from the body
+    html_bytes: usize,
+    /// Title and names of the page
+    metadata: PageMetadata,
+    /// Href of the canonical link of the page
+    canonical: Option<String>,
+}
+
+/// Analyze the content of a downloaded page.
+/// Try to extract links to images.
+fn analyze_location(response: &FetchedResponse, options: &ExtractOptions) -> AnalyzedPage {
+    let content_type = header_string(&response.headers, CONTENT_TYPE).unwrap_or_default();
+    let parsed = if is_html(&content_type) {
+        analyze_page(&decode_text(&response.body, &content_type), &options.parse)
+    } else {
+        ParsedPage {
+            refs: Vec::new(),
+            metadata: PageMetadata::default(),
+            canonical: None,
+        }
+    };
+    AnalyzedPage {
+        refs: parsed.refs,
+        html_bytes: response.body.len(),
+        metadata: parsed.metadata,
+        canonical: parsed.canonical,
+    }
+}
+
+/// Fetch the canonical page declared by a page, if it is another page.
+/// Returns its analysis and the url it was served from, only one hop is followed.
+fn analyze_canonical_page(session: &Session, page: &AnalyzedPage, page_url: &Url) -> Option<(AnalyzedPage, Url)> {
+    let mut canonical_url = page_url.join(page.canonical.as_deref()?).ok()?;
+    canonical_url.set_fragment(None);
+    if &canonical_url == page_url || !matches!(canonical_url.scheme(), "http" | "https") {
+        return None;
+    }
+    trace!("{}, no icons declared, trying canonical {}", page_url, canonical_url);
+    let response = session.fetch_html(&canonical_url).ok()?;
+    if !(200..300).contains(&response.status) {
+        return None;
+    }
+    let url = response.url.clone();
+    Some((analyze_location(&response, session.options), url))
+}
+
+/// Fetch `/sitemap.xml` of the site and analyze the first html page it lists.
+/// Returns the analysis, with the sitemap bytes included, and the url of that page.
+fn analyze_sitemap_page(session: &Session, base_url: &Url) -> Option<(AnalyzedPage, Url)> {
+    let sitemap_url = base_url.join("/sitemap.xml").ok()?;
+    let response = session.fetcher.fetch_page(&sitemap_url, session.options.max_html_bytes).ok()?;
+    if !(200..300).contains(&response.status) {
+        return None;
+    }
+    let content_type = header_string(&response.headers, CONTENT_TYPE).unwrap_or_default();
+    let sitemap = decode_text(&response.body, &content_type);
+    let sitemap_bytes = response.body.len();
+    let page_url = sitemap_locations(&sitemap)
+        .iter()
+        .filter_map(|location| sitemap_url.join(location).ok())
+        .find(|url| {
+            let path = url.path();
+            url != base_url && !path.ends_with(".xml") && !path.ends_with(".xml.gz")
+        })?;
+    trace!("{}, no icons declared, trying {} from sitemap", base_url, page_url);
+    let response = session.fetch_html(&page_url).ok()?;
+    let mut page = analyze_location(&response, session.options);
+    page.html_bytes += sitemap_bytes;
+    Some((page, page_url))
+}
+
+/// Decode a body with the charset of the content type, utf-8 if there is none
+fn decode_text(data: &[u8], content_type: &str) -> String {
+    let encoding = charset(content_type)
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .unwrap_or(UTF_8);
+    let (text, _, _) = encoding.decode(data);
+    text.into_owned()
+}
+
+/// Charset parameter of a content type
+fn charset(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if name.trim().eq_ignore_ascii_case("charset") {
+            Some(value.trim().trim_matches('"'))
+        } else {
+            None
+        }
+    })
+}
+
+fn is_html(content_type: &str) -> bool {
+    content_type.starts_with("text/html")
+}
+
+/// Analyze a page that was downloaded by some other http client.
+/// Only html pages are searched, for other content types the list is empty.
+/// AMP pages are searched like any other page.
+/// # Arguments
+/// * `content_type` - Value of the Content-Type header of the page
+/// * `body` - Text content of the page
+/// # Returns
+/// List of icon references as written in the page, relative ones are not resolved
+/// # Example
+/// ```rust
+/// use website_icon_extract::extract_from_content;
+///
+/// let html = r#"<html><head><link rel="icon" href="/icon.png"></head></html>"#;
+/// let list = extract_from_content("text/html; charset=utf-8", html);
+/// assert_eq!(list, vec!["/icon.png"]);
+/// assert!(extract_from_content("application/json", html).is_empty());
+/// ```
+pub fn extract_from_content(content_type: &str, body: &str) -> Vec<String> {
+    if is_html(content_type) {
+        parse::icon_hrefs(body, &ParseOptions::default())
+    } else {
+        Vec::new()
+    }
+}
+
+/// Decode a html document with a known encoding and return its icon references
+/// joined to the url of the document. No charset detection happens,
+/// also a byte order mark does not change the encoding.
+/// # Arguments
+/// * `bytes` - Raw body of the page
+/// * `encoding` - Encoding label like `utf-8` or `windows-1252`
+/// * `base_url` - Url of the page
+/// # Example
+/// ```rust
+/// use website_icon_extract::{extract_from_bytes, IconError, Url};
+///
+/// let base_url = Url::parse("https://example.com/").unwrap();
+/// let html = b"<link rel=icon href=/ic\xf4ne.png>";
+/// let list = extract_from_bytes(html, "windows-1252", &base_url).unwrap();
+/// assert_eq!(list, vec!["https://example.com/ic%C3%B4ne.png"]);
+/// assert!(matches!(extract_from_bytes(html, "klingon", &base_url), Err(IconError::UnknownEncoding(_))));
+/// ```
+pub fn extract_from_bytes(bytes: &[u8], encoding: &str, base_url: &Url) -> Result<Vec<String>, IconError> {
+    let decoder = Encoding::for_label(encoding.trim().as_bytes())
+        .ok_or_else(|| IconError::UnknownEncoding(encoding.to_string()))?;
+    let html = decoder.decode_without_bom_handling(bytes).0;
+    Ok(parse::icon_urls(&html, base_url, &ParseOptions::default())
+        .into_iter()
+        .map(String::from)
+        .collect())
+}
 
-    list
+/// Search a html document for icon references, without any networking.
+/// It never panics, whatever the input, which makes it the entry point for fuzzing.
+/// # Example
+/// ```rust
+/// use website_icon_extract::extract_refs_from_html;
+///
+/// assert_eq!(extract_refs_from_html("<link rel=icon href=/icon.png>"), vec!["/icon.png"]);
+/// assert!(extract_refs_from_html("<<link rel=\"</>").is_empty());
+/// ```
+pub fn extract_refs_from_html(html: &str) -> Vec<String> {
+    parse::icon_hrefs(html, &ParseOptions::default())
 }
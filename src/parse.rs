@@ -0,0 +1,600 @@
+//! Analysis of html and sitemap documents, without any networking.
+//! The functions take the text of a document that was downloaded by any means.
+
+use imagesize::ImageType;
+use log::warn;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::collections::HashMap;
+use url::Url;
+
+use crate::IconSource;
+
+/// Values of `<meta name="...">` that declare an icon in their `content`.
+/// Names are compared ignoring ascii case, pages write them in any mix.
+pub const DEFAULT_META_NAMES: &[&str] = &[
+    "msapplication-TileImage",
+    "msapplication-square70x70logo",
+    "msapplication-square150x150logo",
+    "msapplication-square310x310logo",
+    "msapplication-wide310x150logo",
+];
+
+/// Values of `<meta property="...">` that declare an image in their `content`.
+/// Like the attribute names, the property values are compared ignoring ascii case.
+pub const DEFAULT_META_PROPERTIES: &[&str] = &["og:image"];
+
+/// Values of `<meta name="...">` that declare a brand image for social networks
+pub const DEFAULT_SOCIAL_META_NAMES: &[&str] = &["twitter:image"];
+
+/// Values of `<meta property="...">` that declare a brand image for social networks
+pub const DEFAULT_SOCIAL_META_PROPERTIES: &[&str] = &["og:logo"];
+
+/// Values of `<link rel="...">` that declare an icon in their `href`
+pub const DEFAULT_LINK_RELS: &[&str] = &["shortcut icon", "icon"];
+
+/// Values of `<link rel="...">` that declare an apple touch icon in their `href`
+pub const DEFAULT_APPLE_TOUCH_RELS: &[&str] = &["apple-touch-icon"];
+
+/// Options for analyzing the html of a page
+#[derive(Clone, Debug)]
+pub struct ParseOptions {
+    /// Also search inside conditional comments like `<!--[if IE]>...<![endif]-->`,
+    /// where old pages often declare their Windows tile images
+    /// # Example
+    /// ```rust
+    /// use website_icon_extract::parse::{icon_hrefs, ParseOptions};
+    ///
+    /// let html = r#"<!--[if IE]><link rel="shortcut icon" href="/ie.ico"><![endif]-->"#;
+    /// let options = ParseOptions { conditional_comments: true, ..Default::default() };
+    /// assert_eq!(icon_hrefs(html, &options), vec!["/ie.ico"]);
+    /// assert!(icon_hrefs(html, &ParseOptions::default()).is_empty());
+    /// ```
+    pub conditional_comments: bool,
+    /// Parsing continues after malformed markup, but stops when this many errors
+    /// in a row happen without the parser moving forward
+    pub max_stalled_errors: usize,
+    /// Also capture the `<title>`, `og:title` and `og:site_name` of the page,
+    /// returned as [`crate::ExtractionResult::metadata`]
+    /// # Example
+    /// ```rust,no_run
+    /// use website_icon_extract::{ExtractOptions, ImageLink, ParseOptions};
+    ///
+    /// let options = ExtractOptions {
+    ///     parse: ParseOptions { page_metadata: true, ..Default::default() },
+    ///     ..Default::default()
+    /// };
+    /// let result = ImageLink::from_website_detailed("https://example.com", &options).unwrap();
+    /// println!("{:?}", result.metadata.label());
+    /// ```
+    pub page_metadata: bool,
+    /// If the page declares no icons at all, use the `<source srcset>` candidates
+    /// of `<picture>` elements instead, where some templates put their logo
+    /// # Example
+    /// ```rust
+    /// use website_icon_extract::parse::{icon_hrefs, ParseOptions};
+    ///
+    /// let html = r#"<picture class="logo">
+    ///     <source srcset="/logo.webp 1x, /logo@2x.webp 2x" type="image/webp">
+    ///     <img src="/logo.png">
+    /// </picture>"#;
+    /// let options = ParseOptions { picture_sources: true, ..Default::default() };
+    /// assert_eq!(icon_hrefs(html, &options), vec!["/logo.webp", "/logo@2x.webp"]);
+    /// assert!(icon_hrefs(html, &ParseOptions::default()).is_empty());
+    /// ```
+    pub picture_sources: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            conditional_comments: false,
+            max_stalled_errors: 8,
+            page_metadata: false,
+            picture_sources: false,
+        }
+    }
+}
+
+/// Names of a page that can label its icon
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PageMetadata {
+    /// Text of the `<title>` element, with whitespace collapsed
+    pub title: Option<String>,
+    /// Content of `<meta property="og:title">`
+    pub og_title: Option<String>,
+    /// Content of `<meta property="og:site_name">`
+    pub site_name: Option<String>,
+}
+
+impl PageMetadata {
+    /// Best name for the site: the site name, then the Open Graph title, then the title
+    pub fn label(&self) -> Option<&str> {
+        self.site_name
+            .as_deref()
+            .or(self.og_title.as_deref())
+            .or(self.title.as_deref())
+    }
+
+    /// Take `og:title` and `og:site_name` from a meta element, the first one of each wins
+    fn check_meta(&mut self, name: &[u8], attrs_hashed: &HashMap<String, String>) {
+        if name != b"meta" {
+            return;
+        }
+        let (property, content) = match (attrs_hashed.get("property"), attrs_hashed.get("content")) {
+            (Some(property), Some(content)) => (property, collapse_whitespace(content)),
+            _ => return,
+        };
+        let field = if property.eq_ignore_ascii_case("og:title") {
+            &mut self.og_title
+        } else if property.eq_ignore_ascii_case("og:site_name") {
+            &mut self.site_name
+        } else {
+            return;
+        };
+        if field.is_none() && !content.is_empty() {
+            *field = Some(content);
+        }
+    }
+}
+
+/// Reference to an icon as found in a page
+#[derive(Clone, Debug)]
+pub(crate) struct IconRef {
+    /// Link target as written in the page
+    pub href: String,
+    pub source: IconSource,
+    /// Largest size from the `sizes` attribute
+    pub declared_size: Option<(usize, usize)>,
+    /// All sizes from the `sizes` attribute
+    pub declared_sizes: Vec<(usize, usize)>,
+    /// Format from `og:image:type`
+    pub declared_type: Option<ImageType>,
+    /// The `sizes` attribute contains `any` and the icon is declared as svg
+    /// by its `type` or the extension of its href, used for scalable icons
+    pub declared_any: bool,
+}
+
+/// Icon references of a html document as written in it, relative ones are not resolved
+/// # Example
+/// ```rust
+/// use website_icon_extract::parse::{icon_hrefs, ParseOptions};
+///
+/// let html = r#"<link rel="icon" href="icon.png"><meta property="og:image" content="/og.jpg">"#;
+/// assert_eq!(icon_hrefs(html, &ParseOptions::default()), vec!["icon.png", "/og.jpg"]);
+///
+/// // twitter:image is a name and og:logo a property, the other way round is not recognized
+/// let html = r#"<meta name="twitter:image" content="/card.png"><meta property="og:logo" content="/logo.png">
+///     <meta property="twitter:image" content="/no.png"><meta name="og:logo" content="/no.png">"#;
+/// assert_eq!(icon_hrefs(html, &ParseOptions::default()), vec!["/card.png", "/logo.png"]);
+/// ```
+pub fn icon_hrefs(content: &str, options: &ParseOptions) -> Vec<String> {
+    analyze_content(content, options)
+        .into_iter()
+        .map(|icon_ref| icon_ref.href)
+        .collect()
+}
+
+/// Icon references of a html document joined to the url of the document.
+/// References that can not be joined are left out.
+/// # Example
+/// ```rust
+/// use url::Url;
+/// use website_icon_extract::parse::{icon_urls, ParseOptions};
+///
+/// let page = Url::parse("https://example.com/app/").unwrap();
+/// let html = r#"<link rel="icon" href="icon.png">"#;
+/// let urls = icon_urls(html, &page, &ParseOptions::default());
+/// assert_eq!(urls[0].as_str(), "https://example.com/app/icon.png");
+///
+/// let page = Url::parse("http://[::1]:8080/app/").unwrap();
+/// let html = r#"<link rel="icon" href="icon.png"><link rel="icon" href="//[2001:db8::1]/favicon.ico">"#;
+/// let urls = icon_urls(html, &page, &ParseOptions::default());
+/// assert_eq!(urls[0].as_str(), "http://[::1]:8080/app/icon.png");
+/// assert_eq!(urls[1].as_str(), "http://[2001:db8::1]/favicon.ico");
+/// ```
+pub fn icon_urls(content: &str, base_url: &Url, options: &ParseOptions) -> Vec<Url> {
+    analyze_content(content, options)
+        .into_iter()
+        .filter_map(|icon_ref| base_url.join(&icon_ref.href).ok())
+        .collect()
+}
+
+/// Title and names of a html document, see [`PageMetadata`]
+/// # Example
+/// ```rust
+/// use website_icon_extract::parse::page_metadata;
+///
+/// let html = r#"<head><title>
+///     Docs &amp; Guides
+/// </title><meta property="og:title" content="Docs"></head>"#;
+/// let metadata = page_metadata(html);
+/// assert_eq!(metadata.title.as_deref(), Some("Docs & Guides"));
+/// assert_eq!(metadata.label(), Some("Docs"));
+/// ```
+pub fn page_metadata(content: &str) -> PageMetadata {
+    let options = ParseOptions {
+        page_metadata: true,
+        ..Default::default()
+    };
+    analyze_page(content, &options).metadata
+}
+
+/// Search html content for links to icons and return them
+pub(crate) fn analyze_content(content: &str, options: &ParseOptions) -> Vec<IconRef> {
+    analyze_page(content, options).refs
+}
+
+/// What was found in the html of a page
+pub(crate) struct ParsedPage {
+    /// References to icons
+    pub refs: Vec<IconRef>,
+    /// Title and names, only if the options ask for them
+    pub metadata: PageMetadata,
+    /// Href of the first `<link rel="canonical">`
+    pub canonical: Option<String>,
+}
+
+/// Search html content for links to icons, and for the metadata
+/// of the page if the options ask for it
+pub(crate) fn analyze_page(content: &str, options: &ParseOptions) -> ParsedPage {
+    let mut reader = Reader::from_str(content);
+    reader.trim_text(true);
+    reader.check_end_names(false);
+    let mut buf = Vec::new();
+    let mut list: Vec<IconRef> = Vec::new();
+    let mut metadata = PageMetadata::default();
+    let mut title: Option<String> = None;
+    let mut canonical: Option<String> = None;
+    let mut pictures = 0;
+    let mut picture_refs: Vec<IconRef> = Vec::new();
+    let mut stalled_errors = 0;
+    let mut last_error_position = None;
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e))
+                if options.page_metadata
+                    && metadata.title.is_none()
+                    && e.name().local_name().as_ref() == b"title" =>
+            {
+                title = Some(String::new());
+            }
+            Ok(Event::Start(ref e)) if options.picture_sources && e.name().local_name().as_ref() == b"picture" => {
+                pictures += 1;
+            }
+            Ok(Event::Empty(ref e)) | Ok(Event::Start(ref e)) if is_searched(e, pictures > 0) => {
+                // the attributes are hashed once and used by all checks of the element
+                let local_name = e.name().local_name();
+                let name = local_name.as_ref();
+                let attrs_hashed = attr_to_hash(&reader, e.html_attributes());
+                if options.page_metadata {
+                    metadata.check_meta(name, &attrs_hashed);
+                }
+                if canonical.is_none() {
+                    canonical = canonical_href(name, &attrs_hashed);
+                }
+                if pictures > 0 && name == b"source" {
+                    picture_refs.extend(srcset_refs(&attrs_hashed));
+                }
+                list.extend(element_refs(name, &attrs_hashed));
+                if let Some(image_type) = og_image_type(name, &attrs_hashed) {
+                    // structured properties follow the og:image they belong to
+                    let last_image = list
+                        .iter_mut()
+                        .rev()
+                        .find(|icon_ref| icon_ref.source == IconSource::OpenGraph);
+                    if let Some(icon_ref) = last_image {
+                        icon_ref.declared_type.get_or_insert(image_type);
+                    }
+                }
+            }
+            Ok(Event::Comment(ref e)) if options.conditional_comments => {
+                if let Ok(comment) = reader.decoder().decode(e) {
+                    if let Some(inner) = conditional_comment_content(&comment) {
+                        list.extend(analyze_content(inner, options));
+                    }
+                }
+            }
+            Ok(Event::End(ref e)) if title.is_some() && e.name().local_name().as_ref() == b"title" => {
+                let text = collapse_whitespace(&title.take().unwrap_or_default());
+                if !text.is_empty() {
+                    metadata.title = Some(text);
+                }
+            }
+            Ok(Event::End(ref e)) if pictures > 0 && e.name().local_name().as_ref() == b"picture" => pictures -= 1,
+            Ok(Event::End(_)) => {}
+            Ok(Event::Text(ref e)) => {
+                if let Some(title) = title.as_mut() {
+                    // entities unknown to xml like &nbsp; are kept as written
+                    match e.unescape() {
+                        Ok(text) => title.push_str(&text),
+                        Err(_) => title.push_str(&reader.decoder().decode(e).unwrap_or_default()),
+                    }
+                    title.push(' ');
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => {
+                let position = reader.buffer_position();
+                warn!("Error at position {}: {:?}", position, e);
+                if last_error_position == Some(position) {
+                    stalled_errors += 1;
+                } else {
+                    stalled_errors = 1;
+                    last_error_position = Some(position);
+                }
+                if stalled_errors >= options.max_stalled_errors {
+                    warn!("Parser does not advance at position {}, giving up", position);
+                    break;
+                }
+            }
+            _ => (), // There are several other `Event`s we do not consider here
+        }
+        buf.clear();
+    }
+    if list.is_empty() {
+        list = picture_refs;
+    }
+    ParsedPage {
+        refs: list,
+        metadata,
+        canonical,
+    }
+}
+
+/// Elements whose attributes are looked at, `<source>` only inside a `<picture>`
+fn is_searched(e: &quick_xml::events::BytesStart<'_>, in_picture: bool) -> bool {
+    match e.name().local_name().as_ref() {
+        b"meta" | b"link" => true,
+        b"source" => in_picture,
+        _ => false,
+    }
+}
+
+/// Urls of the `srcset` of a `<source>` element, without their descriptors
+fn srcset_refs(attrs_hashed: &HashMap<String, String>) -> Vec<IconRef> {
+    let srcset = match attrs_hashed.get("srcset") {
+        Some(srcset) => srcset,
+        None => return Vec::new(),
+    };
+    srcset
+        .split(',')
+        .filter_map(|candidate| candidate.split_whitespace().next())
+        .map(|href| IconRef {
+            href: href.to_string(),
+            source: IconSource::Picture,
+            declared_size: None,
+            declared_sizes: Vec::new(),
+            declared_type: None,
+            declared_any: false,
+        })
+        .collect()
+}
+
+/// Href of a `<link rel="canonical">` element
+fn canonical_href(name: &[u8], attrs_hashed: &HashMap<String, String>) -> Option<String> {
+    if name != b"link" {
+        return None;
+    }
+    let rel = attrs_hashed.get("rel")?;
+    if !rel.split_whitespace().any(|token| token.eq_ignore_ascii_case("canonical")) {
+        return None;
+    }
+    attrs_hashed.get("href").map(|href| href.trim().to_string())
+}
+
+/// Trim and replace runs of whitespace with a single space
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Text of all `<loc>` elements of a sitemap
+pub(crate) fn sitemap_locations(content: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(content);
+    reader.trim_text(true);
+    let mut buf = Vec::new();
+    let mut in_loc = false;
+    let mut list: Vec<String> = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) => in_loc = e.name().local_name().as_ref() == b"loc",
+            Ok(Event::End(_)) => in_loc = false,
+            Ok(Event::Text(ref e)) if in_loc => {
+                if let Ok(text) = e.unescape() {
+                    list.push(text.trim().to_string());
+                }
+            }
+            Ok(Event::CData(ref e)) if in_loc => {
+                if let Ok(text) = reader.decoder().decode(e) {
+                    list.push(text.trim().to_string());
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    list
+}
+
+/// Markup inside a conditional comment, the part between `[if ...]>` and `<![endif]`
+fn conditional_comment_content(comment: &str) -> Option<&str> {
+    let comment = comment.trim();
+    if !comment.starts_with("[if") {
+        return None;
+    }
+    let (_, inner) = comment.split_once("]>")?;
+    Some(inner.trim_end().trim_end_matches("<![endif]"))
+}
+
+/// Position of the closing `</head` tag, searching from byte `from` on.
+/// Case is ignored, the rest of the tag does not need to be there yet.
+pub(crate) fn head_end(data: &[u8], from: usize) -> Option<usize> {
+    const CLOSE: &[u8] = b"</head";
+    let start = from.min(data.len());
+    data[start..]
+        .windows(CLOSE.len())
+        .position(|window| window.eq_ignore_ascii_case(CLOSE))
+        .map(|position| start + position)
+}
+
+/// Attributes of an element by lowercase name, in any order.
+/// A repeated attribute is reported as an error by the reader and skipped,
+/// so the first occurrence wins like in browsers.
+pub(crate) fn attr_to_hash<R>(
+    reader: &quick_xml::Reader<R>,
+    e: quick_xml::events::attributes::Attributes,
+) -> HashMap<String, String> {
+    let attrs_hashed: HashMap<String, String> = e
+        .filter_map(|x| x.ok())
+        .filter_map(|x| {
+            let name = x.key.local_name();
+            let key = reader.decoder().decode(name.as_ref()).ok()?;
+            let value = reader.decoder().decode(&x.value).ok()?;
+            Some((key.to_lowercase(), value.to_string()))
+        })
+        .collect();
+    attrs_hashed
+}
+
+/// Return the content attribute if the key attribute is one of the names.
+/// Names are compared ignoring ascii case without allocating.
+fn extract(
+    attrs_hashed: &HashMap<String, String>,
+    names: &[&str],
+    key_name: &str,
+    content: &str,
+    source: IconSource,
+) -> Option<IconRef> {
+    let name = attrs_hashed.get(key_name)?;
+    let content = attrs_hashed.get(content)?;
+    if names.iter().any(|known| known.eq_ignore_ascii_case(name)) {
+        let declared_sizes = attrs_hashed.get("sizes").map(|sizes| parse_sizes(sizes)).unwrap_or_default();
+        Some(IconRef {
+            href: content.to_string(),
+            source,
+            declared_size: declared_sizes.iter().copied().max_by_key(|(width, height)| width * height),
+            declared_sizes,
+            declared_type: None,
+            declared_any: declares_scalable(attrs_hashed, content),
+        })
+    } else {
+        None
+    }
+}
+
+/// `sizes="any"` of an element declaring a svg, other formats like an ico with
+/// `sizes="any"` are not scalable and have to be measured
+fn declares_scalable(attrs_hashed: &HashMap<String, String>, href: &str) -> bool {
+    let any = attrs_hashed
+        .get("sizes")
+        .is_some_and(|sizes| sizes.split_whitespace().any(|size| size.eq_ignore_ascii_case("any")));
+    let svg_type = attrs_hashed
+        .get("type")
+        .is_some_and(|mime| mime.split(';').next().unwrap_or("").trim().eq_ignore_ascii_case("image/svg+xml"));
+    let path = href.trim().split(['?', '#']).next().unwrap_or("").as_bytes();
+    let svg_extension = path.len() >= 4 && path[path.len() - 4..].eq_ignore_ascii_case(b".svg");
+    any && (svg_type || svg_extension)
+}
+
+/// Sizes of a space separated `sizes` attribute like `16x16 32x32`, `any` is left out
+fn parse_sizes(sizes: &str) -> Vec<(usize, usize)> {
+    sizes.split_whitespace().filter_map(parse_size).collect()
+}
+
+/// Parse a `sizes` value like `32x32`
+fn parse_size(size: &str) -> Option<(usize, usize)> {
+    let (width, height) = size.split_once(['x', 'X'])?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/// Format declared by a `<meta property="og:image:type">` element
+fn og_image_type(name: &[u8], attrs_hashed: &HashMap<String, String>) -> Option<ImageType> {
+    if name != b"meta" {
+        return None;
+    }
+    let property = attrs_hashed.get("property")?;
+    if !property.eq_ignore_ascii_case("og:image:type") {
+        return None;
+    }
+    image_type_from_mime(attrs_hashed.get("content")?)
+}
+
+/// Image type for a mime type like `image/png`, `None` if it is not a known image format
+pub(crate) fn image_type_from_mime(mime: &str) -> Option<ImageType> {
+    let essence = mime.split(';').next()?.trim().to_lowercase();
+    match essence.as_str() {
+        "image/png" | "image/apng" => Some(ImageType::Png),
+        "image/x-icon" | "image/vnd.microsoft.icon" | "image/ico" | "image/icon" => Some(ImageType::Ico),
+        "image/jpeg" | "image/jpg" | "image/pjpeg" => Some(ImageType::Jpeg),
+        "image/gif" => Some(ImageType::Gif),
+        "image/webp" => Some(ImageType::Webp),
+        "image/bmp" | "image/x-ms-bmp" => Some(ImageType::Bmp),
+        "image/tiff" => Some(ImageType::Tiff),
+        "image/heif" | "image/heic" => Some(ImageType::Heif),
+        "image/jxl" => Some(ImageType::Jxl),
+        "image/vnd.adobe.photoshop" => Some(ImageType::Psd),
+        _ => None,
+    }
+}
+
+/// Icon references of elements that were parsed by other means, for example
+/// the nodes of a DOM built with another html parser. Each element is given by its
+/// name and its attributes, the same rules as for html text apply.
+/// Element and attribute names are compared ignoring ascii case, for a repeated
+/// attribute the first occurrence wins.
+/// # Example
+/// ```rust
+/// use website_icon_extract::parse::icon_hrefs_from_elements;
+///
+/// let elements = vec![
+///     ("link", vec![("rel", "stylesheet"), ("href", "/style.css")]),
+///     ("LINK", vec![("REL", "icon"), ("href", "/favicon.png")]),
+///     ("meta", vec![("property", "og:image"), ("content", "/og.jpg")]),
+/// ];
+/// assert_eq!(icon_hrefs_from_elements(elements), vec!["/favicon.png", "/og.jpg"]);
+/// ```
+pub fn icon_hrefs_from_elements<'a, E, A>(elements: E) -> Vec<String>
+where
+    E: IntoIterator<Item = (&'a str, A)>,
+    A: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let mut hrefs = Vec::new();
+    for (name, attributes) in elements {
+        let mut attrs_hashed: HashMap<String, String> = HashMap::new();
+        for (key, value) in attributes {
+            attrs_hashed.entry(key.to_lowercase()).or_insert_with(|| value.to_string());
+        }
+        let refs = element_refs(name.to_ascii_lowercase().as_bytes(), &attrs_hashed);
+        hrefs.extend(refs.into_iter().map(|icon_ref| icon_ref.href));
+    }
+    hrefs
+}
+
+/// Icon references of an element given by its lowercase name and its attributes
+fn element_refs(name: &[u8], attrs_hashed: &HashMap<String, String>) -> Vec<IconRef> {
+    let mut list: Vec<IconRef> = Vec::new();
+
+    match name {
+        b"meta" => {
+            let l = extract(attrs_hashed, DEFAULT_META_NAMES, "name", "content", IconSource::MsTile);
+            list.extend(l);
+            let l = extract(attrs_hashed, DEFAULT_META_PROPERTIES, "property", "content", IconSource::OpenGraph);
+            list.extend(l);
+            let l = extract(attrs_hashed, DEFAULT_SOCIAL_META_NAMES, "name", "content", IconSource::Social);
+            list.extend(l);
+            let l = extract(attrs_hashed, DEFAULT_SOCIAL_META_PROPERTIES, "property", "content", IconSource::Social);
+            list.extend(l);
+        }
+        b"link" => {
+            let l = extract(attrs_hashed, DEFAULT_APPLE_TOUCH_RELS, "rel", "href", IconSource::AppleTouch);
+            list.extend(l);
+            let l = extract(attrs_hashed, DEFAULT_LINK_RELS, "rel", "href", IconSource::Link);
+            list.extend(l);
+        }
+        _ => {}
+    };
+
+    list
+}
@@ -0,0 +1,50 @@
+//! Bounded worker pool for running blocking requests concurrently.
+//! Threads are scoped, so they are always joined before [`run_bounded`] returns,
+//! also when a job panics.
+
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+
+/// Run `job` for every item on at most `workers` threads.
+/// The results are returned in the order of the items.
+pub(crate) fn run_bounded<T, R, F>(items: Vec<T>, workers: usize, job: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let workers = workers.min(items.len());
+    if workers <= 1 {
+        return items.into_iter().map(job).collect();
+    }
+
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let queue = &queue;
+            let job = &job;
+            scope.spawn(move || loop {
+                let next = match queue.lock() {
+                    Ok(mut queue) => queue.next(),
+                    Err(_) => None,
+                };
+                match next {
+                    Some((index, item)) => {
+                        if sender.send((index, job(item))).is_err() {
+                            break;
+                        }
+                    }
+                    None => break,
+                }
+            });
+        }
+    });
+    drop(sender);
+
+    let mut results: Vec<(usize, R)> = receiver.into_iter().collect();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
@@ -0,0 +1,12 @@
+//! The types needed for most extractions, for a single glob import.
+//! # Example
+//! ```rust
+//! use website_icon_extract::prelude::*;
+//!
+//! let url = "data:image/svg+xml,%3Csvg%20width='32'%20height='32'%3E%3C/svg%3E";
+//! let icon: Result<ImageLink, IconError> = ImageLink::new_with_options(url, &ExtractOptions::default());
+//! let icons = vec![icon.unwrap()];
+//! assert_eq!(icons.best().map(|icon| icon.source), Some(IconSource::Direct));
+//! ```
+
+pub use crate::{ExtractOptions, IconError, IconListExt, IconSource, ImageLink, ImageType};
@@ -0,0 +1,176 @@
+//! Global request rate limit and per host concurrency limit,
+//! shared by all threads of an extraction or batch.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use reqwest::{Method, Url};
+
+use crate::{FetchedResponse, IconError, IconFetcher};
+
+/// Spaces requests evenly so that at most the configured number start per second
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    interval: Duration,
+    /// Earliest start of the next request
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    /// `None` if `per_second` is not a positive number
+    pub fn new(per_second: f64) -> Option<Self> {
+        if !(per_second > 0.0 && per_second.is_finite()) {
+            return None;
+        }
+        Some(RateLimiter {
+            interval: Duration::from_secs_f64(1.0 / per_second),
+            next: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Block until the next request may start
+    pub fn acquire(&self) {
+        let wait = match self.next.lock() {
+            Ok(mut next) => {
+                let now = Instant::now();
+                let start = (*next).max(now);
+                *next = start + self.interval;
+                start - now
+            }
+            Err(_) => Duration::from_secs(0),
+        };
+        if wait > Duration::from_secs(0) {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// Fetcher that waits for the rate limiter before every request
+#[derive(Debug)]
+pub(crate) struct RateLimitedFetcher {
+    pub inner: Arc<dyn IconFetcher>,
+    pub limiter: Arc<RateLimiter>,
+}
+
+impl IconFetcher for RateLimitedFetcher {
+    fn fetch_page(&self, url: &Url, limit: usize) -> Result<FetchedResponse, IconError> {
+        self.limiter.acquire();
+        self.inner.fetch_page(url, limit)
+    }
+
+    fn fetch_page_with(
+        &self,
+        url: &Url,
+        limit: usize,
+        method: &Method,
+        body: Option<&[u8]>,
+    ) -> Result<FetchedResponse, IconError> {
+        self.limiter.acquire();
+        self.inner.fetch_page_with(url, limit, method, body)
+    }
+
+    fn fetch_head(&self, url: &Url, limit: usize) -> Result<FetchedResponse, IconError> {
+        self.limiter.acquire();
+        self.inner.fetch_head(url, limit)
+    }
+
+    fn fetch_range(&self, url: &Url, length: u64) -> Result<FetchedResponse, IconError> {
+        self.limiter.acquire();
+        self.inner.fetch_range(url, length)
+    }
+}
+
+/// Allows at most the configured number of requests in flight to any single host
+#[derive(Debug)]
+pub(crate) struct HostLimiter {
+    max_per_host: usize,
+    /// Requests in flight by host
+    in_flight: Mutex<HashMap<String, usize>>,
+    released: Condvar,
+}
+
+impl HostLimiter {
+    /// `None` if `max_per_host` is 0
+    pub fn new(max_per_host: usize) -> Option<Self> {
+        if max_per_host == 0 {
+            return None;
+        }
+        Some(HostLimiter {
+            max_per_host,
+            in_flight: Mutex::new(HashMap::new()),
+            released: Condvar::new(),
+        })
+    }
+
+    /// Block until a request to the host of the url may start.
+    /// The slot is given back when the guard is dropped.
+    pub fn acquire(&self, url: &Url) -> HostSlot<'_> {
+        let host = url.host_str().unwrap_or("").to_string();
+        if let Ok(in_flight) = self.in_flight.lock() {
+            let busy = |in_flight: &mut HashMap<String, usize>| {
+                in_flight.get(&host).copied().unwrap_or(0) >= self.max_per_host
+            };
+            if let Ok(mut in_flight) = self.released.wait_while(in_flight, busy) {
+                *in_flight.entry(host.clone()).or_insert(0) += 1;
+            }
+        }
+        HostSlot { limiter: self, host }
+    }
+}
+
+/// Request slot of a [`HostLimiter`], released on drop
+pub(crate) struct HostSlot<'a> {
+    limiter: &'a HostLimiter,
+    host: String,
+}
+
+impl Drop for HostSlot<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.limiter.in_flight.lock() {
+            if let Some(count) = in_flight.get_mut(&self.host) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    in_flight.remove(&self.host);
+                }
+            }
+        }
+        self.limiter.released.notify_all();
+    }
+}
+
+/// Fetcher that waits for a free slot of the host before every request
+#[derive(Debug)]
+pub(crate) struct HostLimitedFetcher {
+    pub inner: Arc<dyn IconFetcher>,
+    pub limiter: Arc<HostLimiter>,
+}
+
+impl IconFetcher for HostLimitedFetcher {
+    fn fetch_page(&self, url: &Url, limit: usize) -> Result<FetchedResponse, IconError> {
+        let _slot = self.limiter.acquire(url);
+        self.inner.fetch_page(url, limit)
+    }
+
+    fn fetch_page_with(
+        &self,
+        url: &Url,
+        limit: usize,
+        method: &Method,
+        body: Option<&[u8]>,
+    ) -> Result<FetchedResponse, IconError> {
+        let _slot = self.limiter.acquire(url);
+        self.inner.fetch_page_with(url, limit, method, body)
+    }
+
+    fn fetch_head(&self, url: &Url, limit: usize) -> Result<FetchedResponse, IconError> {
+        let _slot = self.limiter.acquire(url);
+        self.inner.fetch_head(url, limit)
+    }
+
+    fn fetch_range(&self, url: &Url, length: u64) -> Result<FetchedResponse, IconError> {
+        let _slot = self.limiter.acquire(url);
+        self.inner.fetch_range(url, length)
+    }
+}
@@ -0,0 +1,116 @@
+//! Downloading complete icons and normalizing them to a square PNG,
+//! for consumers that need a ready-to-use image and not only its url,
+//! or reducing them to a perceptual hash for comparing icons across sites.
+
+use std::io::Cursor;
+
+use image::error::{ImageError, ParameterError, ParameterErrorKind};
+use image::imageops::{self, FilterType};
+use image::{ImageOutputFormat, RgbaImage};
+
+use crate::{check_scheme, data_url, ExtractOptions, IconError, ImageLink, Session};
+
+/// Largest icon file that is downloaded for resizing
+const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Rows and columns of compared pixels in a perceptual hash, 8x8 gives 64 bits
+const HASH_SIZE: u32 = 8;
+
+impl ImageLink {
+    /// Download the complete icon and scale it to a `target` x `target` PNG.
+    /// The aspect ratio is kept, non-square icons are centered on a transparent background.
+    /// Vector images can not be decoded.
+    /// # Arguments
+    /// * `target` - Width and height of the resulting image in pixels, at least 1
+    /// * `options` - Options for the request
+    /// # Example
+    /// ```rust
+    /// use website_icon_extract::{ExtractOptions, ImageLink};
+    /// # let mut png = std::io::Cursor::new(Vec::new());
+    /// # image::RgbaImage::new(64, 32).write_to(&mut png, image::ImageOutputFormat::Png).unwrap();
+    /// # let encoded: String = png.into_inner().iter().map(|b| format!("%{:02X}", b)).collect();
+    ///
+    /// // `encoded` is a percent-encoded 64x32 png
+    /// let url = format!("data:image/png,{}", encoded);
+    /// let options = ExtractOptions::default();
+    /// let icon = ImageLink::new_with_options(&url, &options).unwrap();
+    /// let resized = icon.fetch_resized(16, &options).unwrap();
+    /// let decoded = image::load_from_memory(&resized).unwrap();
+    /// assert_eq!((decoded.width(), decoded.height()), (16, 16));
+    /// ```
+    pub fn fetch_resized(&self, target: u32, options: &ExtractOptions) -> Result<Vec<u8>, IconError> {
+        if target == 0 {
+            let kind = ParameterErrorKind::DimensionMismatch;
+            return Err(ImageError::Parameter(ParameterError::from_kind(kind)).into());
+        }
+        let scaled = image::load_from_memory(&self.fetch_image(options)?)?
+            .resize(target, target, FilterType::Lanczos3)
+            .to_rgba8();
+        let mut canvas = RgbaImage::new(target, target);
+        let x = (target - scaled.width()) / 2;
+        let y = (target - scaled.height()) / 2;
+        imageops::overlay(&mut canvas, &scaled, x.into(), y.into());
+
+        let mut png = Cursor::new(Vec::new());
+        canvas.write_to(&mut png, ImageOutputFormat::Png)?;
+        Ok(png.into_inner())
+    }
+
+    /// Download the complete icon and compute its difference hash (dHash).
+    /// Visually similar icons get hashes that differ in few bits, also when they
+    /// were encoded differently or in another size. Compare two hashes with
+    /// `(a ^ b).count_ones()`, a distance below about 10 usually means the same image.
+    /// Vector images can not be decoded.
+    /// # Arguments
+    /// * `options` - Options for the request
+    /// # Example
+    /// ```rust
+    /// use website_icon_extract::{ExtractOptions, ImageLink};
+    /// # let encode = |width, height| {
+    /// #     let image = image::RgbaImage::from_fn(width, height, |x, _| {
+    /// #         let shade = (x * 255 / width) as u8;
+    /// #         image::Rgba([shade, shade, shade, 255])
+    /// #     });
+    /// #     let mut png = std::io::Cursor::new(Vec::new());
+    /// #     image::DynamicImage::ImageRgba8(image).write_to(&mut png, image::ImageOutputFormat::Png).unwrap();
+    /// #     let encoded: String = png.into_inner().iter().map(|b| format!("%{:02X}", b)).collect();
+    /// #     format!("data:image/png,{}", encoded)
+    /// # };
+    ///
+    /// // the same gradient as a 64x64 and a 16x16 png
+    /// let options = ExtractOptions::default();
+    /// let large = ImageLink::new_with_options(&encode(64, 64), &options).unwrap();
+    /// let small = ImageLink::new_with_options(&encode(16, 16), &options).unwrap();
+    /// let distance = (large.phash(&options).unwrap() ^ small.phash(&options).unwrap()).count_ones();
+    /// assert!(distance < 10);
+    /// ```
+    pub fn phash(&self, options: &ExtractOptions) -> Result<u64, IconError> {
+        let gray = image::load_from_memory(&self.fetch_image(options)?)?
+            .resize_exact(HASH_SIZE + 1, HASH_SIZE, FilterType::Triangle)
+            .to_luma8();
+        let mut hash = 0;
+        for y in 0..HASH_SIZE {
+            for x in 0..HASH_SIZE {
+                // one bit per pair of horizontally neighbouring pixels
+                let brighter = gray.get_pixel(x + 1, y)[0] > gray.get_pixel(x, y)[0];
+                hash = (hash << 1) | u64::from(brighter);
+            }
+        }
+        Ok(hash)
+    }
+
+    /// Download the complete icon file
+    fn fetch_image(&self, options: &ExtractOptions) -> Result<Vec<u8>, IconError> {
+        check_scheme(&self.url)?;
+        let data = if self.url.scheme() == "data" {
+            data_url::decode(&self.url).ok_or(IconError::InvalidDataUrl)?.data
+        } else {
+            let session = Session::new(options)?;
+            session.icon_fetcher.fetch_page(&self.url, MAX_IMAGE_BYTES)?.body
+        };
+        if data.is_empty() {
+            return Err(IconError::EmptyBody);
+        }
+        Ok(data)
+    }
+}
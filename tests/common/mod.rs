@@ -0,0 +1,198 @@
+//! Mock transports shared by the integration tests: a local http server
+//! and a fetcher answering from a closure
+#![allow(dead_code)]
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+
+use website_icon_extract::{ExtractOptions, FetchedResponse, HeaderMap, IconError, IconFetcher, Url};
+
+/// Request received by the server of [`serve`]
+#[derive(Debug)]
+pub struct Request {
+    /// Http method, empty if the client did not speak http
+    pub method: String,
+    /// Path and query
+    pub path: String,
+    /// Header lines, names in lower case
+    pub headers: Vec<(String, String)>,
+    /// Body, read up to the Content-Length
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Value of the header with the lower case `name`
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Serve every connection to a local port with the bytes returned by `respond`,
+/// one connection at a time. Returns the address like `127.0.0.1:8080`.
+pub fn serve<F>(respond: F) -> String
+where
+    F: Fn(&Request) -> Vec<u8> + Send + 'static,
+{
+    serve_on("127.0.0.1:0", respond)
+}
+
+/// Same as [`serve`] on another address, like `[::1]:0` for IPv6
+pub fn serve_on<F>(addr: &str, respond: F) -> String
+where
+    F: Fn(&Request) -> Vec<u8> + Send + 'static,
+{
+    let listener = TcpListener::bind(addr).unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            let request = read_request(&mut stream);
+            // clients stop reading once they have enough of a large body
+            let _ = stream.write_all(&respond(&request));
+        }
+    });
+    addr
+}
+
+/// Read the request head and its body, stops early for clients that do not speak http
+fn read_request(stream: &mut impl Read) -> Request {
+    let mut data: Vec<u8> = Vec::new();
+    let mut chunk = [0; 4096];
+    let head_end = loop {
+        if let Some(end) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end + 4;
+        }
+        if matches!(data.first(), Some(byte) if !byte.is_ascii_uppercase()) {
+            break data.len();
+        }
+        match stream.read(&mut chunk) {
+            Ok(0) | Err(_) => break data.len(),
+            Ok(length) => data.extend_from_slice(&chunk[..length]),
+        }
+    };
+    let head = String::from_utf8_lossy(&data[..head_end]).to_string();
+    let mut lines = head.lines();
+    let mut start = lines.next().unwrap_or("").split(' ');
+    let method = start.next().unwrap_or("").to_string();
+    let path = start.next().unwrap_or("").to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+    let mut request = Request {
+        method,
+        path,
+        headers,
+        body: data[head_end..].to_vec(),
+    };
+    let length: usize = request.header("content-length").and_then(|value| value.parse().ok()).unwrap_or(0);
+    while request.body.len() < length {
+        match stream.read(&mut chunk) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => request.body.extend_from_slice(&chunk[..read]),
+        }
+    }
+    request
+}
+
+/// Raw http response with the given status line like `200 OK`, headers and body,
+/// the connection is closed after it
+pub fn response(status: &str, headers: &[(&str, &str)], body: &[u8]) -> Vec<u8> {
+    let mut bytes = format!("HTTP/1.1 {}\r\n", status).into_bytes();
+    for (name, value) in headers {
+        bytes.extend_from_slice(format!("{}: {}\r\n", name, value).as_bytes());
+    }
+    bytes.extend_from_slice(format!("Content-Length: {}\r\nConnection: close\r\n\r\n", body.len()).as_bytes());
+    bytes.extend_from_slice(body);
+    bytes
+}
+
+/// Raw `200 OK` response with an html page
+pub fn html_response(html: &str) -> Vec<u8> {
+    response("200 OK", &[("Content-Type", "text/html")], html.as_bytes())
+}
+
+/// Raw `404 Not Found` response without a body
+pub fn not_found() -> Vec<u8> {
+    response("404 Not Found", &[], b"")
+}
+
+/// Fetcher answering all requests with a closure, which gets
+/// `None` for page requests and the requested length for ranges
+pub struct Fixture<F>(pub F);
+
+impl<F> fmt::Debug for Fixture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Fixture")
+    }
+}
+
+impl<F> IconFetcher for Fixture<F>
+where
+    F: Fn(&Url, Option<u64>) -> FetchedResponse + Send + Sync,
+{
+    fn fetch_page(&self, url: &Url, _limit: usize) -> Result<FetchedResponse, IconError> {
+        Ok((self.0)(url, None))
+    }
+
+    fn fetch_range(&self, url: &Url, length: u64) -> Result<FetchedResponse, IconError> {
+        Ok((self.0)(url, Some(length)))
+    }
+}
+
+/// Options with a [`Fixture`] as fetcher
+pub fn fixture<F>(respond: F) -> ExtractOptions
+where
+    F: Fn(&Url, Option<u64>) -> FetchedResponse + Send + Sync + 'static,
+{
+    ExtractOptions {
+        fetcher: Some(Arc::new(Fixture(respond))),
+        ..Default::default()
+    }
+}
+
+/// Response with the given status and body, without headers
+pub fn file(url: &Url, status: u16, body: &[u8]) -> FetchedResponse {
+    FetchedResponse {
+        url: url.clone(),
+        status,
+        headers: HeaderMap::new(),
+        body: body.to_vec(),
+    }
+}
+
+/// `200` response with an html page
+pub fn page(url: &Url, html: &str) -> FetchedResponse {
+    let mut headers = HeaderMap::new();
+    headers.insert("content-type", "text/html".parse().unwrap());
+    FetchedResponse {
+        headers,
+        ..file(url, 200, html.as_bytes())
+    }
+}
+
+/// `404` response without a body
+pub fn missing(url: &Url) -> FetchedResponse {
+    file(url, 404, b"")
+}
+
+/// Start of a png file, enough to read its size
+pub fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    png.extend_from_slice(&width.to_be_bytes());
+    png.extend_from_slice(&height.to_be_bytes());
+    png
+}
+
+/// Inline svg icon with the given size, measured without a request
+pub fn svg_data_url(size: usize) -> String {
+    format!("data:image/svg+xml,%3Csvg%20width='{0}'%20height='{0}'%3E%3C/svg%3E", size)
+}
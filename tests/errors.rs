@@ -0,0 +1,93 @@
+mod common;
+
+use common::{file, fixture, html_response, missing, not_found, page, png, response, serve};
+use website_icon_extract::{AuditOutcome, ExtractOptions, IconError, ImageLink};
+
+#[test]
+fn plain_http_server_fails_tls() {
+    let addr = serve(|_| response("400 Bad Request", &[], b""));
+    let result = ImageLink::new_with_options(format!("https://{}/favicon.ico", addr), &ExtractOptions::default());
+    assert!(matches!(result, Err(IconError::Tls(_))));
+}
+
+#[test]
+fn redirect_to_itself_is_too_many_redirects() {
+    let addr = serve(|_| response("302 Found", &[("Location", "/favicon.ico")], b""));
+    let result = ImageLink::new_with_options(format!("http://{}/favicon.ico", addr), &ExtractOptions::default());
+    assert!(matches!(result, Err(IconError::TooManyRedirects(_))));
+}
+
+#[test]
+fn fragment_hrefs_are_empty() {
+    let addr = serve(|request| match request.path.as_str() {
+        "/" => html_response(
+            r##"<svg><use href="#icon"/></svg><link rel="icon" href="#icon"><link rel="icon" href=" # ">"##,
+        ),
+        _ => not_found(),
+    });
+    let audit = ImageLink::audit(format!("http://{}/", addr), &ExtractOptions::default()).unwrap();
+    let fragments: Vec<&AuditOutcome> = audit
+        .entries
+        .iter()
+        .filter(|entry| entry.href.is_some())
+        .map(|entry| &entry.outcome)
+        .collect();
+    assert_eq!(fragments.len(), 2);
+    assert!(fragments
+        .iter()
+        .all(|outcome| matches!(outcome, AuditOutcome::Filtered(IconError::EmptyHref))));
+}
+
+#[test]
+fn unfilled_templates_are_not_fetched() {
+    let options = fixture(|url, length| match length {
+        None => page(
+            url,
+            r#"<link rel="icon" href="{{ favicon }}">
+                <link rel="icon" href="%ASSET_URL%/favicon.png"><link rel="icon" href="/%C3%A9cole%20icon.png">"#,
+        ),
+        Some(_) => missing(url),
+    });
+    let result = ImageLink::from_website_detailed("https://example.com/", &options).unwrap();
+    let templates: Vec<&str> = result
+        .errors
+        .iter()
+        .filter_map(|(_, error)| match error {
+            IconError::TemplatePlaceholder(href) => Some(href.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(templates, vec!["{{ favicon }}", "%ASSET_URL%/favicon.png"]);
+}
+
+#[test]
+fn empty_body_drops_only_that_icon() {
+    let options = fixture(|url, length| match (url.path(), length) {
+        (_, None) => page(url, r#"<link rel="icon" href="/empty.png"><link rel="icon" href="/icon.png">"#),
+        ("/empty.png", Some(_)) => file(url, 200, b""),
+        (_, Some(_)) => file(url, 200, &png(32, 32)),
+    });
+    let result = ImageLink::new_with_options("https://example.com/empty.png", &options);
+    assert!(matches!(result, Err(IconError::EmptyBody)));
+
+    let result = ImageLink::from_website_detailed("https://example.com/", &options).unwrap();
+    let urls: Vec<&str> = result.icons.iter().map(|icon| icon.url.path()).collect();
+    assert_eq!(urls, vec!["/icon.png", "/favicon.ico"]);
+    let empty: Vec<&str> = result
+        .errors
+        .iter()
+        .filter(|(_, error)| matches!(error, IconError::EmptyBody))
+        .map(|(url, _)| url.path())
+        .collect();
+    assert_eq!(empty, vec!["/empty.png"]);
+}
+
+#[test]
+fn huge_png_header_is_implausible() {
+    let url = "data:image/png,%89PNG%0D%0A%1A%0A%00%00%00%0DIHDR%7F%FF%FF%FF%00%00%00%10";
+    let result = ImageLink::new_with_options(url, &ExtractOptions::default());
+    assert!(matches!(
+        result,
+        Err(IconError::ImplausibleSize { width: 0x7fff_ffff, height: 16 })
+    ));
+}
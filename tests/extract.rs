@@ -0,0 +1,307 @@
+mod common;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use common::{file, fixture, html_response, missing, not_found, page, png, response, serve, serve_on};
+use website_icon_extract::{
+    extract_from_content, extract_refs_from_html, ExtractOptions, FetchedResponse, IconListExt, ImageLink, ImageType, Url,
+};
+
+#[test]
+fn probes_stop_reading_servers_without_range_support() {
+    let addr = serve(|request| match request.path.as_str() {
+        "/" => html_response(r#"<link rel="icon" href="/icon.png">"#),
+        "/icon.png" => {
+            // an old server: no range support and no content length, the body ends with the connection
+            let mut bytes = b"HTTP/1.0 200 OK\r\nContent-Type: image/png\r\n\r\n".to_vec();
+            bytes.extend_from_slice(&png(32, 32));
+            bytes.resize(bytes.len() + 1024 * 1024, 0);
+            bytes
+        }
+        _ => not_found(),
+    });
+    let result = ImageLink::from_website_detailed(format!("http://{}/", addr), &ExtractOptions::default()).unwrap();
+    assert_eq!((result.icons[0].width, result.icons[0].height), (32, 32));
+    assert_eq!(result.icons[0].file_size, None);
+    assert!(result.icon_bytes_total <= 262);
+}
+
+#[test]
+fn raw_refs_are_kept_as_written() {
+    let options = fixture(|url, length| match length {
+        None => page(url, r#"<link rel="icon" href="javascript:void(0)"><link rel="icon" href=" {{icon}} ">"#),
+        Some(_) => missing(url),
+    });
+    let result = ImageLink::from_website_detailed("https://example.com/", &options).unwrap();
+    assert!(result.icons.is_empty());
+    assert_eq!(result.raw_refs, vec!["javascript:void(0)", " {{icon}} "]);
+}
+
+#[test]
+fn no_refs_found_and_all_probes_failed() {
+    let options = fixture(|url, length| match (url.path(), length) {
+        ("/declared", None) => page(url, r#"<link rel="icon" href="/icon.png">"#),
+        (_, None) => page(url, "<title>No icons</title>"),
+        (_, Some(_)) => missing(url),
+    });
+    let result = ImageLink::from_website_detailed("https://example.com/", &options).unwrap();
+    assert!(result.no_refs_found && !result.all_probes_failed);
+    let result = ImageLink::from_website_detailed("https://example.com/declared", &options).unwrap();
+    assert!(!result.no_refs_found && result.all_probes_failed);
+}
+
+#[test]
+fn sizes_any_is_scalable_without_a_request() {
+    // the declared svg is on a server that can not be reached
+    let addr = serve(|request| match request.path.as_str() {
+        "/" => html_response(r#"<link rel="icon" sizes="any" href="http://127.0.0.1:9/icon.svg">"#),
+        _ => not_found(),
+    });
+    let result = ImageLink::from_website_detailed(format!("http://{}/", addr), &ExtractOptions::default()).unwrap();
+    let icon = &result.icons[0];
+    assert!(icon.scalable);
+    assert!(!icon.measured);
+    assert_eq!(icon.elapsed, None);
+}
+
+#[test]
+fn sizes_any_ico_is_still_measured() {
+    let options = fixture(|url, length| match (url.path(), length) {
+        (_, None) => page(
+            url,
+            r#"<link rel="icon" href="/favicon.ico" sizes="any"><link rel="icon" href="/icon.png" sizes="192x192">"#,
+        ),
+        ("/favicon.ico", Some(_)) => file(url, 200, ICO_16),
+        (_, Some(_)) => file(url, 200, &png(192, 192)),
+    });
+    let icons = ImageLink::from_website_with_options("https://example.com/", &options).unwrap();
+    let ico = icons.iter().find(|icon| icon.url.path() == "/favicon.ico").unwrap();
+    assert!(ico.measured && !ico.scalable);
+    assert_eq!((ico.width, ico.height), (16, 16));
+    assert_eq!(icons.best().unwrap().url.path(), "/icon.png");
+}
+
+/// Ico directory with a single 16x16 image
+const ICO_16: &[u8] = b"\0\0\x01\0\x01\0\x10\x10\0\0\x01\0\x20\0\0\x01\0\0\x16\0\0\0";
+
+#[test]
+fn declared_sizes_keep_all_sizes() {
+    let options = fixture(|url, length| match length {
+        None => page(url, r#"<link rel="icon" sizes="16x16 32x32 48x48" href="/favicon.ico">"#),
+        Some(_) => file(url, 200, ICO_16),
+    });
+    let icons = ImageLink::from_website_with_options("https://example.com/", &options).unwrap();
+    assert_eq!(icons[0].declared_sizes, vec![(16, 16), (32, 32), (48, 48)]);
+    assert_eq!(icons[0].declared_size, Some((48, 48)));
+}
+
+#[test]
+fn size_mismatch_compares_measured_and_declared() {
+    let options = fixture(|url, length| match (url.path(), length) {
+        (_, None) => page(url, r#"<link rel="icon" sizes="32x32" href="/icon.png">"#),
+        ("/icon.png", Some(_)) => file(url, 200, &png(16, 16)),
+        (_, Some(_)) => missing(url),
+    });
+    let icons = ImageLink::from_website_with_options("https://example.com/", &options).unwrap();
+    assert_eq!(icons[0].declared_size, Some((32, 32)));
+    assert_eq!((icons[0].width, icons[0].height), (16, 16));
+    assert!(icons[0].size_mismatch);
+}
+
+#[test]
+fn contained_sizes_list_the_ico_directory() {
+    // ico file with 8 images, the 256x256 one last
+    let options = fixture(|url, length| {
+        let mut ico = vec![0, 0, 1, 0, 8, 0];
+        for size in [16u8, 24, 32, 48, 64, 96, 128, 0] {
+            ico.extend_from_slice(&[size, size, 0, 0, 1, 0, 32, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+        }
+        ico.resize(64 * 1024, 0);
+        ico.truncate(length.unwrap_or(u64::MAX) as usize);
+        file(url, 206, &ico)
+    });
+    let icon = ImageLink::new_with_options("https://example.com/favicon.ico", &options).unwrap();
+    assert_eq!(icon.contained_sizes.len(), 8);
+    assert_eq!(icon.contained_sizes[0], (16, 16));
+    assert_eq!((icon.width, icon.height), (256, 256));
+}
+
+#[test]
+fn partial_tells_whether_the_range_was_honored() {
+    let options = fixture(|url, _| {
+        let status = if url.path() == "/ranged.png" { 206 } else { 200 };
+        file(url, status, &png(32, 32))
+    });
+    assert!(ImageLink::new_with_options("https://example.com/ranged.png", &options).unwrap().partial);
+    assert!(!ImageLink::new_with_options("https://example.com/full.png", &options).unwrap().partial);
+}
+
+#[test]
+fn revalidate_picks_up_changes() {
+    // square png of the current size, nothing for size 0
+    let size = Arc::new(AtomicU32::new(32));
+    let current = size.clone();
+    let options = fixture(move |url, length| match (length, current.load(Ordering::SeqCst)) {
+        (None, _) => page(url, r#"<link rel="icon" href="/icon.png">"#),
+        (Some(_), 0) => missing(url),
+        (Some(_), size) => file(url, 200, &png(size, size)),
+    });
+    let root = Url::parse("https://example.com/").unwrap();
+    let mut icon = ImageLink::from_pages(std::slice::from_ref(&root), &options).unwrap().remove(0);
+    assert_eq!(icon.url.path(), "/icon.png");
+    size.store(64, Ordering::SeqCst);
+    assert!(icon.revalidate(&options).unwrap());
+    assert_eq!((icon.width, icon.height), (64, 64));
+    assert_eq!(icon.pages, vec![root]);
+    size.store(0, Ordering::SeqCst);
+    assert!(!icon.revalidate(&options).unwrap());
+    assert_eq!(icon.width, 64);
+}
+
+#[test]
+fn favicon_for_host_accepts_ipv6_literals() {
+    let addr = serve_on("[::1]:0", |request| match request.path.as_str() {
+        "/favicon.ico" => response("200 OK", &[], &png(48, 48)),
+        _ => not_found(),
+    });
+    let icon = ImageLink::favicon_for_host(&addr, &ExtractOptions::default()).unwrap().unwrap();
+    assert_eq!(icon.url.as_str(), format!("http://{}/favicon.ico", addr));
+    assert_eq!((icon.width, icon.height), (48, 48));
+}
+
+#[test]
+fn from_pages_merges_icons_of_all_pages() {
+    let options = fixture(|url, length| match (url.path(), length) {
+        ("/", None) => page(url, r#"<link rel="icon" href="/a.png">"#),
+        (_, None) => page(url, r#"<link rel="icon" href="/a.png"><link rel="apple-touch-icon" href="/b.png">"#),
+        ("/favicon.ico", Some(_)) => missing(url),
+        (_, Some(_)) => file(url, 200, &png(32, 32)),
+    });
+    let root = Url::parse("https://example.com/").unwrap();
+    let index = Url::parse("https://example.com/index.html").unwrap();
+    let icons = ImageLink::from_pages(&[root.clone(), index.clone()], &options).unwrap();
+    assert_eq!(icons.len(), 2);
+    assert_eq!(icons[0].url.path(), "/a.png");
+    assert_eq!(icons[0].pages, vec![root, index.clone()]);
+    assert_eq!(icons[1].url.path(), "/b.png");
+    assert_eq!(icons[1].pages, vec![index]);
+}
+
+#[test]
+fn from_website_by_size_prefers_png() {
+    let options = fixture(|url, length| match (url.path(), length) {
+        (_, None) => page(
+            url,
+            r#"<link rel="icon" href="/a.ico"><link rel="icon" href="/b.png"><link rel="icon" href="/c.png">"#,
+        ),
+        ("/a.ico", Some(_)) => file(url, 200, b"\0\0\x01\0\x01\0\x20\x20\0\0\x01\0\x20\0\0\x04\0\0\x16\0\0\0"),
+        ("/b.png", Some(_)) => file(url, 200, &png(32, 32)),
+        ("/c.png", Some(_)) => file(url, 200, &png(16, 16)),
+        (_, Some(_)) => missing(url),
+    });
+    let icons = ImageLink::from_website_by_size("https://example.com/", &options).unwrap();
+    assert_eq!(icons.keys().collect::<Vec<_>>(), vec![&(16, 16), &(32, 32)]);
+    assert_eq!(icons[&(32, 32)].image_type, Some(ImageType::Png));
+}
+
+#[test]
+fn relative_refs_resolve_against_the_final_url() {
+    let options = fixture(|url, length| match length {
+        None => FetchedResponse {
+            url: Url::parse("https://example.com/app/").unwrap(),
+            ..page(url, r#"<link rel="icon" href="icon.png">"#)
+        },
+        Some(_) => file(url, 200, &png(32, 32)),
+    });
+    let result = ImageLink::from_website_detailed("http://example.com/app", &options).unwrap();
+    assert_eq!(result.final_url.as_str(), "https://example.com/app/");
+    assert_eq!(result.icons[0].url.as_str(), "https://example.com/app/icon.png");
+    assert_eq!(result.icons[1].url.as_str(), "https://example.com/favicon.ico");
+}
+
+#[test]
+fn redirected_page_reports_both_urls() {
+    let addr = serve(|request| match request.path.as_str() {
+        "/start" => response("302 Found", &[("Location", "/site/home/")], b""),
+        "/site/home/" => html_response(r#"<link rel="icon" href="icon.png">"#),
+        "/site/home/icon.png" => response("200 OK", &[], &png(32, 32)),
+        _ => not_found(),
+    });
+    let result = ImageLink::from_website_detailed(format!("http://{}/start", addr), &ExtractOptions::default()).unwrap();
+    assert_eq!(result.requested_url.as_str(), format!("http://{}/start", addr));
+    assert_eq!(result.final_url.as_str(), format!("http://{}/site/home/", addr));
+    assert_ne!(result.requested_url, result.final_url);
+    assert_eq!(result.icons.len(), 1);
+    assert_eq!(result.icons[0].url, result.final_url.join("icon.png").unwrap());
+    assert_eq!((result.icons[0].width, result.icons[0].height), (32, 32));
+}
+
+#[test]
+fn noscript_links_are_found() {
+    // fallback links inside noscript, next to the usual tag manager iframe
+    let html = r#"<head>
+        <noscript><iframe src="https://www.googletagmanager.com/ns.html?id=GTM-X" style="display:none"></iframe></noscript>
+        <noscript><img src="/pixel.gif"><link rel="icon" href="/noscript.png"></noscript>
+        <link rel="shortcut icon" href="/favicon.ico">
+    </head>"#;
+    assert_eq!(extract_refs_from_html(html), vec!["/noscript.png", "/favicon.ico"]);
+}
+
+#[test]
+fn attribute_order_does_not_matter_and_the_first_repeat_wins() {
+    assert_eq!(extract_refs_from_html(r#"<link href="/x.ico" rel="icon">"#), vec!["/x.ico"]);
+    assert_eq!(extract_refs_from_html(r#"<link rel="icon" rel="shortcut icon" href="/x.ico">"#), vec!["/x.ico"]);
+    assert!(extract_refs_from_html(r#"<link rel="stylesheet" rel="icon" href="/x.css">"#).is_empty());
+    assert_eq!(extract_refs_from_html(r#"<link rel="icon" href="/a.ico" href="/b.ico">"#), vec!["/a.ico"]);
+}
+
+#[test]
+fn tile_image_names_ignore_case() {
+    let html = r#"<meta name="msapplication-TileImage" content="/tile.png">
+        <meta name="MSAPPLICATION-TILEIMAGE" content="/upper.png">
+        <meta name="msapplication-tileimage" content="/lower.png">"#;
+    assert_eq!(extract_refs_from_html(html), vec!["/tile.png", "/upper.png", "/lower.png"]);
+}
+
+#[test]
+fn og_image_properties_ignore_case() {
+    let html = r#"<meta property="OG:Image" content="/mixed.jpg"><meta PROPERTY="og:IMAGE" CONTENT="/upper.jpg">"#;
+    assert_eq!(extract_refs_from_html(html), vec!["/mixed.jpg", "/upper.jpg"]);
+}
+
+#[test]
+fn amp_pages_are_searched_like_any_other_page() {
+    // the `⚡` attribute, inline scripts and styles and the `amp-` elements do not hide the icons
+    let html = r#"<!doctype html>
+    <html ⚡ lang="en">
+    <head>
+      <meta charset="utf-8">
+      <script async src="https://cdn.ampproject.org/v0.js"></script>
+      <script async custom-element="amp-carousel" src="https://cdn.ampproject.org/v0/amp-carousel-0.1.js"></script>
+      <title>Hello, AMPs</title>
+      <link rel="canonical" href="https://example.com/article.html">
+      <meta name="viewport" content="width=device-width">
+      <script type="application/ld+json">{"@context": "http://schema.org", "headline": "a<b && c>d"}</script>
+      <style amp-boilerplate>body{-webkit-animation:-amp-start 8s steps(1,end) 0s 1 normal both}</style>
+      <noscript><style amp-boilerplate>body{-webkit-animation:none;animation:none}</style></noscript>
+      <style amp-custom>nav > a { color: red }</style>
+      <link href="/favicon-32.png" sizes="32x32" rel="icon" type="image/png">
+      <link sizes=180x180 href=/apple.png rel=apple-touch-icon>
+    </head>
+    <body><amp-img src="logo.png" width="100" height="100" layout="responsive"></amp-img></body>
+    </html>"#;
+    assert_eq!(extract_from_content("text/html", html), vec!["/favicon-32.png", "/apple.png"]);
+}
+
+#[test]
+fn single_quoted_and_unquoted_attributes_are_found() {
+    let html = "<link rel='icon' href='/a.ico'><link rel=apple-touch-icon href=/b.png>";
+    assert_eq!(extract_from_content("text/html", html), vec!["/a.ico", "/b.png"]);
+}
+
+#[test]
+fn broken_markup_does_not_stop_or_hang_the_search() {
+    let html = "<link rel=icon href=/c.png><![CDATA[ <?xml <!DOCTYPE <a b=\"";
+    assert_eq!(extract_from_content("text/html", html), vec!["/c.png"]);
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use common::{not_found, png, response, serve};
+use website_icon_extract::{ExtractOptions, IconError, ImageLink};
+
+#[test]
+fn compressed_ranges_are_read_without_range() {
+    let mut icon = png(32, 32);
+    icon.extend_from_slice(b"\x08\x06\0\0\0");
+    icon.extend((0..600u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8));
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&icon).unwrap();
+    let gzip = encoder.finish().unwrap();
+    // the server sends the icon gzip encoded, also for range requests
+    let addr = serve(move |request| {
+        let headers = [("Content-Type", "image/png"), ("Content-Encoding", "gzip")];
+        match request.header("range") {
+            Some("bytes=0-261") => response("206 Partial Content", &headers, &gzip[..262]),
+            _ => response("200 OK", &headers, &gzip),
+        }
+    });
+    let icon = ImageLink::new(format!("http://{}/icon.png", addr), "TEST", 5).unwrap();
+    assert_eq!((icon.width, icon.height), (32, 32));
+}
+
+#[test]
+fn probes_follow_relative_and_malformed_redirects() {
+    let addr = serve(|request| match request.path.as_str() {
+        "/favicon.ico" => response("302 Found", &[("Location", "icons/favicon.ico")], b""),
+        "/icons/favicon.ico" => {
+            // not valid utf-8, so the http client hands the redirect back instead of following it
+            let mut bytes = b"HTTP/1.1 301 Moved Permanently\r\nLocation: /static/icon\xff.png\r\n".to_vec();
+            bytes.extend_from_slice(b"Content-Length: 0\r\nConnection: close\r\n\r\n");
+            bytes
+        }
+        path if path.starts_with("/static/icon") => response("200 OK", &[], &png(48, 48)),
+        _ => not_found(),
+    });
+    let icon = ImageLink::new_with_options(format!("http://{}/favicon.ico", addr), &ExtractOptions::default()).unwrap();
+    assert_eq!((icon.width, icon.height), (48, 48));
+}
+
+#[test]
+fn malformed_redirects_resolve_against_the_redirecting_url() {
+    let addr = serve(|request| match request.path.as_str() {
+        "/favicon.ico" => response("302 Found", &[("Location", "/icons/start")], b""),
+        "/icons/start" => {
+            let mut bytes = b"HTTP/1.1 302 Found\r\nLocation: real\xff.png\r\n".to_vec();
+            bytes.extend_from_slice(b"Content-Length: 0\r\nConnection: close\r\n\r\n");
+            bytes
+        }
+        path if path.starts_with("/icons/real") => response("200 OK", &[], &png(48, 48)),
+        _ => not_found(),
+    });
+    let icon = ImageLink::new_with_options(format!("http://{}/favicon.ico", addr), &ExtractOptions::default()).unwrap();
+    assert_eq!((icon.width, icon.height), (48, 48));
+}
+
+#[test]
+fn broken_uncompressed_ranges_are_not_refetched() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counted = requests.clone();
+    let addr = serve(move |_| {
+        counted.fetch_add(1, Ordering::SeqCst);
+        // the connection closes long before the announced length
+        let mut bytes = b"HTTP/1.1 206 Partial Content\r\nContent-Length: 262\r\nConnection: close\r\n\r\n".to_vec();
+        bytes.extend_from_slice(&png(32, 32));
+        bytes
+    });
+    let result = ImageLink::new_with_options(format!("http://{}/icon.png", addr), &ExtractOptions::default());
+    assert!(matches!(result, Err(IconError::Io(_))));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,493 @@
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use common::{file, fixture, html_response, missing, not_found, page, png, response, serve, svg_data_url};
+use website_icon_extract::{
+    AuditOutcome, ExtractOptions, FetchedResponse, HeaderMap, IconError, ImageLink, ImageType, Method, ParseOptions,
+    SkipHandler,
+};
+
+#[test]
+fn allowed_types_sniff_octet_stream() {
+    let options = ExtractOptions {
+        allowed_types: Some(vec![ImageType::Png]),
+        ..fixture(|url, _| {
+            let mut headers = HeaderMap::new();
+            headers.insert("content-type", "application/octet-stream".parse().unwrap());
+            FetchedResponse {
+                headers,
+                ..file(url, 200, &png(16, 16))
+            }
+        })
+    };
+    let icon = ImageLink::new_with_options("https://example.com/favicon.png", &options).unwrap();
+    assert_eq!(icon.image_type, Some(ImageType::Png));
+    assert_eq!(icon.content_type.as_deref(), Some("application/octet-stream"));
+}
+
+#[test]
+fn min_icon_bytes_drops_tiny_responses() {
+    let options = ExtractOptions {
+        min_icon_bytes: 30,
+        ..fixture(|url, _| {
+            let mut headers = HeaderMap::new();
+            headers.insert("content-length", "9".parse().unwrap());
+            FetchedResponse {
+                headers,
+                ..file(url, 200, b"not found")
+            }
+        })
+    };
+    let result = ImageLink::new_with_options("https://example.com/favicon.ico", &options);
+    assert!(matches!(result, Err(IconError::TooSmall(9))));
+}
+
+/// Page of 4 MiB without a Content-Length, starting with `start`
+fn huge_page(start: &str) -> Vec<u8> {
+    let mut bytes = b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nConnection: close\r\n\r\n".to_vec();
+    bytes.extend_from_slice(start.as_bytes());
+    bytes.resize(bytes.len() + 4 * 1024 * 1024, b'x');
+    bytes
+}
+
+#[test]
+fn stop_at_head_end_skips_the_body() {
+    let head = format!("<html><head><link rel=icon href=\"{}\"></head><body>", svg_data_url(16));
+    let addr = serve(move |request| match request.path.as_str() {
+        "/" => huge_page(&head),
+        _ => not_found(),
+    });
+    let options = ExtractOptions {
+        max_html_bytes: 8 * 1024 * 1024,
+        stop_at_head_end: true,
+        ..Default::default()
+    };
+    let result = ImageLink::from_website_detailed(format!("http://{}/", addr), &options).unwrap();
+    assert_eq!(result.icons.len(), 1);
+    assert!(result.html_bytes < 64 * 1024);
+}
+
+#[test]
+fn max_head_bytes_stops_in_a_long_head() {
+    let start = format!("<html><head><link rel=icon href=\"{}\"><script>", svg_data_url(16));
+    let addr = serve(move |request| match request.path.as_str() {
+        "/" => huge_page(&start),
+        _ => not_found(),
+    });
+    let options = ExtractOptions {
+        max_head_bytes: Some(32 * 1024),
+        ..Default::default()
+    };
+    let result = ImageLink::from_website_detailed(format!("http://{}/", addr), &options).unwrap();
+    assert_eq!(result.icons.len(), 1);
+    assert_eq!(result.html_bytes, 32 * 1024);
+}
+
+/// Counts the requests in flight and remembers the most at the same time
+#[derive(Debug, Default)]
+struct InFlight {
+    now: AtomicUsize,
+    most: AtomicUsize,
+}
+
+impl InFlight {
+    fn track<T>(&self, request: impl FnOnce() -> T) -> T {
+        let now = self.now.fetch_add(1, Ordering::SeqCst) + 1;
+        self.most.fetch_max(now, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(20));
+        let result = request();
+        self.now.fetch_sub(1, Ordering::SeqCst);
+        result
+    }
+}
+
+#[test]
+fn max_per_host_limits_requests_to_one_host() {
+    let in_flight = Arc::new(InFlight::default());
+    let tracked = in_flight.clone();
+    let options = ExtractOptions {
+        max_concurrency: 4,
+        max_per_host: Some(1),
+        ..fixture(move |url, _| tracked.track(|| missing(url)))
+    };
+    let urls = ["https://example.com/a", "https://example.com/b", "https://example.com/c"];
+    ImageLink::from_websites(&urls, &options).unwrap();
+    assert_eq!(in_flight.most.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn try_all_schemes_falls_back_to_http() {
+    let html = format!(r#"<link rel="icon" href="{}">"#, svg_data_url(16));
+    let host = serve(move |request| match request.path.as_str() {
+        "/" => html_response(&html),
+        _ => not_found(),
+    });
+    let options = ExtractOptions {
+        try_all_schemes: true,
+        ..Default::default()
+    };
+    let result = ImageLink::from_website_detailed(&host, &options).unwrap();
+    assert_eq!(result.requested_url.scheme(), "http");
+    assert_eq!(result.icons.len(), 1);
+}
+
+#[test]
+fn require_success_rejects_error_pages() {
+    let options = ExtractOptions {
+        require_success: true,
+        ..fixture(|url, length| match length {
+            None => FetchedResponse {
+                status: 404,
+                ..page(url, r#"<link rel="icon" href="/hosting-provider.png">"#)
+            },
+            Some(_) => missing(url),
+        })
+    };
+    let result = ImageLink::from_website_with_options("https://example.com/gone", &options);
+    assert!(matches!(result, Err(IconError::PageStatus(404))));
+}
+
+#[test]
+fn strip_query_params_merges_cache_busters() {
+    let options = ExtractOptions {
+        strip_query_params: vec![String::from("_"), String::from("utm_*")],
+        ..fixture(|url, length| match length {
+            None => page(
+                url,
+                r#"<link rel="icon" href="/icon.png?_=1681234567">
+                    <link rel="icon" href="/icon.png?utm_source=feed">
+                    <link rel="icon" href="/icon.png?size=32&utm_medium=rss">
+                    <link rel="icon" href="/icon.png?name=a%20b&utm_source=feed&flag">"#,
+            ),
+            Some(_) => missing(url),
+        })
+    };
+    let audit = ImageLink::audit("https://example.com/", &options).unwrap();
+    let urls: Vec<&str> = audit
+        .entries
+        .iter()
+        .filter_map(|entry| entry.url.as_ref())
+        .map(|url| url.as_str())
+        .collect();
+    assert_eq!(
+        urls[..4],
+        [
+            "https://example.com/icon.png",
+            "https://example.com/icon.png",
+            "https://example.com/icon.png?size=32",
+            "https://example.com/icon.png?name=a%20b&flag",
+        ]
+    );
+    assert!(matches!(audit.entries[1].outcome, AuditOutcome::DuplicateUrl));
+}
+
+#[test]
+fn page_method_posts_the_body() {
+    let html = format!(r#"<link rel="icon" href="{}">"#, svg_data_url(16));
+    let addr = serve(move |request| {
+        if request.method == "POST" && request.path == "/search" && request.body == b"q=icons" {
+            html_response(&html)
+        } else {
+            response("405 Method Not Allowed", &[], b"")
+        }
+    });
+    let options = ExtractOptions {
+        page_method: Method::POST,
+        page_body: Some(b"q=icons".to_vec()),
+        ..Default::default()
+    };
+    let icons = ImageLink::from_website_with_options(format!("http://{}/search", addr), &options).unwrap();
+    assert_eq!(icons.len(), 1);
+}
+
+#[test]
+fn page_method_is_cut_at_the_head_end() {
+    let options = ExtractOptions {
+        page_method: Method::POST,
+        stop_at_head_end: true,
+        ..fixture(|url, length| match length {
+            None => {
+                let html = format!(
+                    r#"<head><link rel="icon" href="{}"></head><body><link rel="icon" href="{}">"#,
+                    svg_data_url(16),
+                    svg_data_url(32)
+                );
+                page(url, &html)
+            }
+            Some(_) => missing(url),
+        })
+    };
+    let icons = ImageLink::from_website_with_options("https://example.com/search", &options).unwrap();
+    assert_eq!(icons.len(), 1);
+    assert_eq!(icons[0].width, 16);
+}
+
+#[test]
+fn follow_canonical_extracts_from_the_canonical_page() {
+    let options = ExtractOptions {
+        follow_canonical: true,
+        ..fixture(|url, length| match (url.path(), length) {
+            ("/article", None) => page(url, &format!(r#"<link rel="icon" href="{}">"#, svg_data_url(16))),
+            (_, None) => page(url, r#"<link rel="canonical" href="/article">"#),
+            (_, Some(_)) => missing(url),
+        })
+    };
+    let result = ImageLink::from_website_detailed("https://example.com/amp/article", &options).unwrap();
+    assert_eq!(result.icons.len(), 1);
+    assert_eq!(result.base_url.as_str(), "https://example.com/article");
+}
+
+#[test]
+fn min_probe_retries_read_late_jpeg_sizes() {
+    // jpeg with 300 bytes of metadata before its size
+    let options = fixture(|url, length| {
+        let mut jpeg = b"\xFF\xD8\xFF\xE1\x01\x2E".to_vec();
+        jpeg.extend_from_slice(&[0; 300]);
+        jpeg.extend_from_slice(b"\xFF\xC0\x00\x11\x08\x00\x20\x00\x20\x03");
+        jpeg.truncate(length.unwrap_or(u64::MAX) as usize);
+        file(url, 206, &jpeg)
+    });
+    let url = "https://example.com/logo.jpg";
+    assert!(ImageLink::new_with_options(url, &options).is_err());
+
+    let options = ExtractOptions {
+        min_probe_retries_on_short_read: 1,
+        ..options
+    };
+    let icon = ImageLink::new_with_options(url, &options).unwrap();
+    assert_eq!((icon.width, icon.height), (32, 32));
+}
+
+#[test]
+fn identity_encoding_for_probes_asks_for_uncompressed_ranges() {
+    let addr = serve(|request| {
+        let identity = request.header("accept-encoding") == Some("identity");
+        match (request.path.as_str(), identity) {
+            ("/", false) => html_response(r#"<link rel="icon" href="/icon.png">"#),
+            ("/icon.png", true) => response("206 Partial Content", &[("Content-Range", "bytes 0-23/24")], &png(32, 32)),
+            _ => not_found(),
+        }
+    });
+    let url = format!("http://{}/", addr);
+    let options = ExtractOptions {
+        identity_encoding_for_probes: true,
+        ..Default::default()
+    };
+    let icons = ImageLink::from_website_with_options(&url, &options).unwrap();
+    assert_eq!(icons.len(), 1);
+    assert_eq!((icons[0].width, icons[0].height), (32, 32));
+    assert!(ImageLink::from_website_with_options(&url, &ExtractOptions::default())
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn max_probe_bytes_grows_the_range() {
+    // png and jpeg files with a size after 300 or 2000 bytes of metadata
+    let ranges = Arc::new(Mutex::new(Vec::new()));
+    let requested = ranges.clone();
+    let options = ExtractOptions {
+        max_probe_bytes: Some(4096),
+        ..fixture(move |url, length| {
+            let length = length.unwrap_or(u64::MAX);
+            requested.lock().unwrap().push(length);
+            let mut body = match url.path() {
+                "/icon.png" => png(32, 32),
+                path => {
+                    let metadata: u16 = if path == "/small.jpg" { 300 } else { 2000 };
+                    let mut jpeg = b"\xFF\xD8\xFF\xE1".to_vec();
+                    jpeg.extend_from_slice(&(metadata + 2).to_be_bytes());
+                    jpeg.resize(jpeg.len() + metadata as usize, 0);
+                    jpeg.extend_from_slice(b"\xFF\xC0\x00\x11\x08\x00\x20\x00\x20\x03");
+                    jpeg
+                }
+            };
+            body.resize(8192, 0);
+            body.truncate(length as usize);
+            file(url, 206, &body)
+        })
+    };
+    let probed = |path: &str| {
+        ranges.lock().unwrap().clear();
+        let icon = ImageLink::new_with_options(format!("https://example.com{}", path), &options).unwrap();
+        assert_eq!((icon.width, icon.height), (32, 32));
+        ranges.lock().unwrap().clone()
+    };
+    assert_eq!(probed("/icon.png"), vec![32]);
+    assert_eq!(probed("/small.jpg"), vec![32, 512]);
+    assert_eq!(probed("/large.jpg"), vec![32, 512, 4096]);
+
+    let options = ExtractOptions {
+        max_probe_bytes: Some(512),
+        ..options
+    };
+    assert!(ImageLink::new_with_options("https://example.com/large.jpg", &options).is_err());
+}
+
+#[test]
+fn short_probes_do_not_merge_different_content() {
+    // red and black pngs of the same size only differ after the header
+    let options = ExtractOptions {
+        max_probe_bytes: Some(4096),
+        dedup_by_content: true,
+        ..fixture(|url, length| match (url.path(), length) {
+            (_, None) => page(url, r#"<link rel="icon" href="/red.png"><link rel="icon" href="/black.png">"#),
+            ("/favicon.ico", Some(_)) => missing(url),
+            (path, Some(length)) => {
+                let mut body = png(32, 32);
+                body.extend_from_slice(b"\x08\x02\0\0\0");
+                let color: u8 = if path == "/red.png" { 0xff } else { 0 };
+                body.resize(1024, color);
+                body.truncate(length as usize);
+                file(url, 206, &body)
+            }
+        })
+    };
+    let icons = ImageLink::from_website_with_options("https://example.com/", &options).unwrap();
+    let paths: Vec<&str> = icons.iter().map(|icon| icon.url.path()).collect();
+    assert_eq!(paths, vec!["/red.png", "/black.png"]);
+    assert!(icons.iter().all(|icon| icon.content_hash.is_none()));
+}
+
+#[test]
+fn page_metadata_captures_the_title() {
+    let options = ExtractOptions {
+        parse: ParseOptions {
+            page_metadata: true,
+            ..Default::default()
+        },
+        ..fixture(|url, length| match length {
+            None => page(url, r#"<title>Home | Example</title><meta property="og:site_name" content="Example">"#),
+            Some(_) => missing(url),
+        })
+    };
+    let result = ImageLink::from_website_detailed("https://example.com/", &options).unwrap();
+    assert_eq!(result.metadata.title.as_deref(), Some("Home | Example"));
+    assert_eq!(result.metadata.label(), Some("Example"));
+}
+
+#[test]
+fn max_concurrency_bounds_the_probes() {
+    let in_flight = Arc::new(InFlight::default());
+    let tracked = in_flight.clone();
+    let html: String = (0..50).map(|i| format!(r#"<link rel="icon" href="/icon-{}.png">"#, i)).collect();
+    let options = ExtractOptions {
+        max_concurrency: 4,
+        ..fixture(move |url, length| match length {
+            None => page(url, &html),
+            Some(_) => tracked.track(|| file(url, 200, &png(16, 16))),
+        })
+    };
+    let icons = ImageLink::from_website_with_options("https://example.com/", &options).unwrap();
+    assert_eq!(icons.len(), 51);
+    assert!(in_flight.most.load(Ordering::SeqCst) <= 4);
+}
+
+#[test]
+fn allowed_types_trust_the_bytes_over_the_declared_type() {
+    let options = ExtractOptions {
+        allowed_types: Some(vec![ImageType::Png]),
+        ..fixture(|url, _| {
+            let mut headers = HeaderMap::new();
+            headers.insert("content-type", "image/webp".parse().unwrap());
+            let body: &[u8] = if url.path() == "/favicon.png" { &png(16, 16) } else { b"not an image" };
+            FetchedResponse {
+                headers,
+                ..file(url, 200, body)
+            }
+        })
+    };
+    let icon = ImageLink::new_with_options("https://example.com/favicon.png", &options).unwrap();
+    assert_eq!(icon.image_type, Some(ImageType::Png));
+    let result = ImageLink::new_with_options("https://example.com/unknown", &options);
+    assert!(matches!(result, Err(IconError::DisallowedType(image_type)) if image_type == "image/webp"));
+}
+
+#[test]
+fn on_skip_reports_every_dropped_candidate() {
+    let skipped = Arc::new(Mutex::new(Vec::new()));
+    let reported = skipped.clone();
+    let options = ExtractOptions {
+        icons_only: true,
+        on_skip: Some(SkipHandler::new(move |entry| {
+            let reason = match &entry.outcome {
+                AuditOutcome::Excluded => "excluded",
+                AuditOutcome::DuplicateUrl => "duplicate",
+                AuditOutcome::Filtered(_) => "filtered",
+                AuditOutcome::Failed(_) => "failed",
+                _ => "other",
+            };
+            let href = entry.href.clone().unwrap_or_default();
+            reported.lock().unwrap().push((href, reason, entry.url.is_some()));
+        })),
+        ..fixture(|url, length| match (url.path(), length) {
+            (_, None) => page(
+                url,
+                r#"<link rel="icon" href="/icon.png"><link rel="icon" href="/icon.png">
+                    <link rel="icon" href="http://[::1"><meta property="og:image" content="/og.jpg">"#,
+            ),
+            ("/icon.png", Some(_)) => file(url, 200, &png(32, 32)),
+            (_, Some(_)) => missing(url),
+        })
+    };
+    let icons = ImageLink::from_website_with_options("https://example.com/", &options).unwrap();
+    assert_eq!(icons.len(), 1);
+    let skipped = skipped.lock().unwrap().clone();
+    let expected = vec![
+        (String::from("/icon.png"), "duplicate", true),
+        (String::from("http://[::1"), "filtered", false),
+        (String::from("/og.jpg"), "excluded", true),
+        (String::new(), "failed", true),
+    ];
+    assert_eq!(skipped, expected);
+}
+
+/// Options for a page with four icons that count every request
+fn counted_requests(requests: Arc<AtomicUsize>) -> ExtractOptions {
+    fixture(move |url, length| {
+        requests.fetch_add(1, Ordering::SeqCst);
+        match (url.path(), length) {
+            (_, None) => page(
+                url,
+                r#"<link rel="icon" href="/0.png"><link rel="icon" href="/1.png">
+                    <link rel="icon" href="/2.png"><link rel="icon" href="/3.png">"#,
+            ),
+            ("/favicon.ico", Some(_)) => missing(url),
+            (_, Some(_)) => file(url, 200, &png(32, 32)),
+        }
+    })
+}
+
+#[test]
+fn max_requests_per_second_spaces_the_requests() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let options = ExtractOptions {
+        max_requests_per_second: Some(20.0),
+        ..counted_requests(requests.clone())
+    };
+    let start = Instant::now();
+    let icons = ImageLink::from_website_with_options("https://example.com/", &options).unwrap();
+    let elapsed = start.elapsed();
+    assert_eq!(icons.len(), 4);
+    // page, four icons and the default favicon, the first one starts right away
+    let requests = requests.load(Ordering::SeqCst);
+    assert_eq!(requests, 6);
+    assert!(elapsed >= Duration::from_secs_f64((requests - 1) as f64 / 20.0 * 0.9));
+}
+
+#[test]
+fn max_requests_per_second_not_positive_is_no_limit() {
+    for rate in [0.0, -1.0, f64::NAN] {
+        let options = ExtractOptions {
+            max_requests_per_second: Some(rate),
+            ..counted_requests(Arc::new(AtomicUsize::new(0)))
+        };
+        let start = Instant::now();
+        let icons = ImageLink::from_website_with_options("https://example.com/", &options).unwrap();
+        assert_eq!(icons.len(), 4);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
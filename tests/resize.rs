@@ -0,0 +1,24 @@
+#![cfg(feature = "image-processing")]
+
+use std::io::Cursor;
+
+use website_icon_extract::{ExtractOptions, IconError, ImageLink};
+
+/// Data url of a transparent png of the given size
+fn png_data_url(width: u32, height: u32) -> String {
+    let mut png = Cursor::new(Vec::new());
+    image::RgbaImage::new(width, height)
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .unwrap();
+    let encoded: String = png.into_inner().iter().map(|b| format!("%{:02X}", b)).collect();
+    format!("data:image/png,{}", encoded)
+}
+
+#[test]
+fn resizing_to_zero_is_an_error() {
+    let options = ExtractOptions::default();
+    let icon = ImageLink::new_with_options(png_data_url(64, 32), &options).unwrap();
+    assert!(matches!(icon.fetch_resized(0, &options), Err(IconError::Decode(_))));
+    let resized = image::load_from_memory(&icon.fetch_resized(1, &options).unwrap()).unwrap();
+    assert_eq!((resized.width(), resized.height()), (1, 1));
+}